@@ -0,0 +1,23 @@
+extern crate cupi;
+
+use cupi::{Logic, DigitalWrite, DigitalRead, delay_ms};
+use cupi::mcp23x17::MCP23017;
+
+fn main() {
+    // the only difference from mcp23s17 example is the constructor
+    let mcp23017 = MCP23017::new(1, 0x20).unwrap();
+    let mut port_out = mcp23017.porta();
+    let mut port_in = mcp23017.portb();
+
+    let mut pinin = port_in.input(0).unwrap();
+    pinin.pull_up().unwrap();
+    let mut pinout = port_out.output(0).unwrap();
+
+    for _ in 0..1000 {
+        match pinin.get().unwrap() {
+            Logic::Low  => pinout.high().unwrap(),
+            Logic::High => pinout.low().unwrap()
+        }
+        delay_ms(50);
+    }
+}
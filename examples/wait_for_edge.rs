@@ -0,0 +1,21 @@
+extern crate cupi;
+
+use std::time::Duration;
+use cupi::{CuPi, DigitalLogic};
+use cupi::sys::Edge;
+
+fn main() {
+    let cupi = CuPi::new().unwrap();
+    let _pull_up = cupi.pin(0).unwrap().pull_up().input();
+
+    let mut pin = cupi.pin_sys(0).unwrap();
+    pin.export().unwrap();
+    let mut pinin = pin.input().unwrap();
+
+    for _ in 0..10 {
+        match pinin.wait_for_edge(Edge::FallingEdge, Some(Duration::from_secs(5))).unwrap() {
+            Some(event) => println!("Pressed at {:?}, level {}", event.timestamp, event.level.logic_level()),
+            None => println!("Timeout."),
+        }
+    }
+}
@@ -1,10 +1,52 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 use map::{SystemMemory, MemoryMap};
-use {Result, Error, Logic, DigitalLogic, DigitalWrite, DigitalRead, CPU, RegisterOperations, delay_hard};
+use {Result, Error, Logic, DigitalLogic, DigitalWrite, DigitalRead, IoPin, CPU, RegisterOperations, delay_hard};
 use super::{GPIORegister, GPIOFunctionSelect, PullUpDnControl, BCM2708_PERI_BASE, BCM2709_PERI_BASE, GPIO_BASE};
 
 pub struct GPIOBase(Mutex<MemoryMap>);
 
+impl GPIOBase {
+    fn lock(&self) -> MutexGuard<MemoryMap> {
+        match self.0.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    // Input, or output when output is set
+    fn function(&self, pin: usize, output: bool) {
+        let gpio_base = self.lock();
+        let func_reg = gpio_base.register(GPIORegister::GPIOFunctionSelect(pin/10));
+        let shift = (pin % 10) * 3;
+        unsafe {
+            func_reg.bitand(!(0b111 << shift));
+            if output {
+                func_reg.bitor(GPIOFunctionSelect::GPIOFunctionOutput.bits() << shift);
+            }
+        }
+    }
+
+    fn write(&self, pin: usize, level: Logic) {
+        let gpio_base = self.lock();
+        let output_reg = match level {
+            Logic::Low  => gpio_base.register(GPIORegister::GPIOPinOutputClear(pin/32)),
+            Logic::High => gpio_base.register(GPIORegister::GPIOPinOutputSet(pin/32))
+        };
+        unsafe { output_reg.write(1 << (pin % 32)); }
+    }
+
+    fn read(&self, pin: usize) -> Logic {
+        let gpio_base = self.lock();
+        let level_reg = gpio_base.register(GPIORegister::GPIOPinLevel(pin/32));
+        unsafe {
+            match level_reg.read() & (1 << (pin % 32)) {
+                0 => Logic::Low,
+                _ => Logic::High
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct GPIO {
     gpio_base: Arc<GPIOBase>
@@ -73,15 +115,7 @@ impl PinOptions {
     }
 
     pub fn input(&self) -> PinInput {
-        {// we should unlock mutex before copy
-            let gpio_base = match self.gpio_base.0.lock() {
-                Ok(guard) => guard,
-                Err(poisoned) => poisoned.into_inner(),
-            };
-            let func_reg = gpio_base.register(GPIORegister::GPIOFunctionSelect(self.pin/10));
-            let shift = (self.pin % 10) * 3;
-            unsafe { func_reg.bitand(!(0b111 << shift)); }
-        }
+        self.gpio_base.function(self.pin, false);
         let pin = PinInput { gpio_base: self.gpio_base.clone(), pin: self.pin };
         match self.pull_ctrl {
             Some(ctrl) => pin.pull_mode(ctrl),
@@ -91,31 +125,20 @@ impl PinOptions {
     }
 
     pub fn output(&self) -> PinOutput {
-        {
-            // set pin as input first
-            let gpio_base = match self.gpio_base.0.lock() {
-                Ok(guard) => guard,
-                Err(poisoned) => poisoned.into_inner(),
-            };
-            let func_reg = gpio_base.register(GPIORegister::GPIOFunctionSelect(self.pin/10));
-            let func_shift = (self.pin % 10) * 3;
-            // Reset port functions
-            unsafe { func_reg.bitand(!(0b111 << func_shift)); }
-
-            // Set default value
-            let output_reg = match self.default_value.logic_level() {
-                Logic::Low  => gpio_base.register(GPIORegister::GPIOPinOutputClear(self.pin/32)),
-                Logic::High => gpio_base.register(GPIORegister::GPIOPinOutputSet(self.pin/32))
-            };
-            let output_shift = self.pin % 32;
-            unsafe { output_reg.write(1 << output_shift); }
-
-            // Make output
-            let bits = GPIOFunctionSelect::GPIOFunctionOutput.bits();
-            unsafe { func_reg.bitor(bits << func_shift); }
-        }
+        // set pin as input first
+        self.gpio_base.function(self.pin, false);
+        // Set default value
+        self.gpio_base.write(self.pin, self.default_value);
+        // Make output
+        self.gpio_base.function(self.pin, true);
         PinOutput { gpio_base: self.gpio_base.clone(), pin: self.pin }
     }
+
+    // Starts as input, the pull mode is applied once
+    pub fn io(&self) -> PinIO {
+        let _ = self.input();
+        PinIO { gpio_base: self.gpio_base.clone(), pin: self.pin }
+    }
 }
 
 #[derive(Clone)]
@@ -126,18 +149,7 @@ pub struct PinInput {
 
 impl PinInput {
     pub fn read(&self) -> Logic {
-        let gpio_base = match self.gpio_base.0.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-        let level_reg = gpio_base.register(GPIORegister::GPIOPinLevel(self.pin/32));
-        let shift = self.pin % 32;
-        unsafe {
-            match level_reg.read() & (1 << shift) {
-                0 => Logic::Low,
-                _ => Logic::High
-            }
-        }
+        self.gpio_base.read(self.pin)
     }
 
     pub fn pull_up(&self) {
@@ -153,10 +165,7 @@ impl PinInput {
     }
 
     fn pull_mode(&self, mode: PullUpDnControl) {
-        let gpio_base = match self.gpio_base.0.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
+        let gpio_base = self.gpio_base.lock();
         let enable_reg = gpio_base.register(GPIORegister::GPIOPinPullUpDownEnable);
         let clock_reg = gpio_base.register(GPIORegister::GPIOPinPullUpDownEnableClock(self.pin/32));
         let shift = self.pin % 32;
@@ -184,17 +193,7 @@ pub struct PinOutput {
 impl PinOutput {
     // FIXME: modify regs without mutex lock
     pub fn write<L: DigitalLogic>(&self, value: L) {
-        let gpio_base = match self.gpio_base.0.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-        let output_reg = match value.logic_level() {
-            Logic::Low  => gpio_base.register(GPIORegister::GPIOPinOutputClear(self.pin/32)),
-            Logic::High => gpio_base.register(GPIORegister::GPIOPinOutputSet(self.pin/32))
-        };
-        let shift = self.pin % 32;
-        let bits  = GPIOFunctionSelect::GPIOFunctionOutput.bits();
-        unsafe { output_reg.write(bits << shift); }
+        self.gpio_base.write(self.pin, value.logic_level());
     }
 }
 
@@ -207,12 +206,35 @@ impl DigitalWrite for PinOutput {
 
 impl Drop for PinOutput {
     fn drop(&mut self) {
-        let gpio_base = match self.gpio_base.0.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-        let func_reg = gpio_base.register(GPIORegister::GPIOFunctionSelect(self.pin/10));
-        let shift = (self.pin % 10) * 3;
-        unsafe { func_reg.bitand(!(0b111 << shift)); }
+        self.gpio_base.function(self.pin, false);
+    }
+}
+
+// Direction switched in place, see IoPin
+pub struct PinIO {
+    gpio_base: Arc<GPIOBase>,
+    pin: usize
+}
+
+impl IoPin for PinIO {
+    fn set_input(&mut self) -> Result<()> {
+        self.gpio_base.function(self.pin, false);
+        Ok(())
+    }
+
+    fn set_output<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+        self.gpio_base.write(self.pin, level.logic_level());
+        self.gpio_base.function(self.pin, true);
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Logic> {
+        Ok(self.gpio_base.read(self.pin))
+    }
+}
+
+impl Drop for PinIO {
+    fn drop(&mut self) {
+        self.gpio_base.function(self.pin, false);
     }
 }
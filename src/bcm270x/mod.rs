@@ -6,7 +6,8 @@ pub use self::gpio::{
     GPIO,
     PinOptions,
     PinInput,
-    PinOutput
+    PinOutput,
+    PinIO
 };
 
 pub struct BCM2708;
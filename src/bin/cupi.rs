@@ -0,0 +1,64 @@
+extern crate cupi;
+
+use std::env;
+use std::process;
+use cupi::i2c::{self, LinuxI2CBus, Probe};
+
+fn usage() -> ! {
+    eprintln!("usage: cupi <command> [args]");
+    eprintln!("");
+    eprintln!("commands:");
+    eprintln!("    i2cdetect [BUS]    scan /dev/i2c-BUS (default 1) and guess the chips");
+    process::exit(1);
+}
+
+fn i2cdetect(bus: usize) -> cupi::Result<()> {
+    let mut adapter = try!(LinuxI2CBus::open(bus));
+    let found = try!(i2c::scan(&mut adapter));
+
+    // same grid as i2cdetect, UU is claimed by a kernel driver
+    println!("     0  1  2  3  4  5  6  7  8  9  a  b  c  d  e  f");
+    for row in 0..8 {
+        print!("{:02x}:", row * 16);
+        for col in 0..16 {
+            let address = row * 16 + col;
+            if address < 0x03 || address > 0x77 {
+                print!("   ");
+                continue;
+            }
+            match found.iter().find(|f| f.address == address) {
+                Some(f) if f.probe == Probe::Busy => print!(" UU"),
+                Some(_) => print!(" {:02x}", address),
+                None => print!(" --")
+            }
+        }
+        println!("");
+    }
+
+    for f in found.iter() {
+        let names: Vec<&str> = f.chips.iter().map(|chip| chip.name).collect();
+        let names = if names.is_empty() { "unknown".to_string() } else { names.join(", ") };
+        let busy = if f.probe == Probe::Busy { " (kernel driver)" } else { "" };
+        println!("0x{:02x}{}: {}", f.address, busy, names);
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let result = match args.get(1).map(|arg| arg.as_str()) {
+        Some("i2cdetect") => {
+            let bus = match args.get(2) {
+                Some(arg) => arg.parse().unwrap_or_else(|_| usage()),
+                None => 1
+            };
+            i2cdetect(bus)
+        },
+        _ => usage()
+    };
+
+    if let Err(err) = result {
+        eprintln!("error: {:?}", err);
+        process::exit(1);
+    }
+}
@@ -29,7 +29,7 @@ pub enum CPU {
 // Raspberry Pi
 
 #[derive(Clone, Copy, Debug)]
-pub enum RaspberryModel { A, B, BP, AP, CM, P0, P2, P3, UN }
+pub enum RaspberryModel { A, B, BP, AP, CM, P0, P0W, P2, P3, UN }
 
 impl<'a> From<&'a RaspberryModel> for &'static str {
     fn from(model: &'a RaspberryModel) -> Self {
@@ -40,6 +40,7 @@ impl<'a> From<&'a RaspberryModel> for &'static str {
             RaspberryModel::AP => "Model A+",
             RaspberryModel::CM => "Compute Module",
             RaspberryModel::P0 => "Zero",
+            RaspberryModel::P0W => "Zero W",
             RaspberryModel::P2 => "Model 2",
             RaspberryModel::P3 => "Model 3",
             RaspberryModel::UN => "Unknown"
@@ -160,6 +161,20 @@ impl Board {
             Hardware::Unknown => Err(Error::UnsupportedHardware)
         }
     }
+
+    // UART on GPIO14/15 without overlays, boards with Bluetooth
+    // give the PL011 to it and leave the mini UART on the header.
+    // Newer boards (3B+, 4, Zero 2 W) are not decoded and get the
+    // serial0 alias, which points at whichever UART is on the header.
+    pub fn uart(&self) -> Result<&'static str> {
+        match self.hardware {
+            Hardware::RaspberryPi(RaspberryModel::P3, _, _, _) |
+            Hardware::RaspberryPi(RaspberryModel::P0W, _, _, _) => Ok("/dev/ttyS0"),
+            Hardware::RaspberryPi(RaspberryModel::UN, _, _, _) => Ok("/dev/serial0"),
+            Hardware::RaspberryPi(..) => Ok("/dev/ttyAMA0"),
+            Hardware::Unknown => Err(Error::UnsupportedHardware)
+        }
+    }
 }
 
 pub fn board() -> Board {
@@ -188,6 +203,7 @@ pub fn board() -> Board {
                                     6 => RaspberryModel::CM,
                                     8 => RaspberryModel::P3,
                                     9 => RaspberryModel::P0, // Zero
+                                    12 => RaspberryModel::P0W,
                                     _ => RaspberryModel::UN
                                 };
                                 let maker = match (revision & (0x0F << 16)) >> 16 {
@@ -238,3 +254,23 @@ pub fn board() -> Board {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Board, Hardware, CPU, RaspberryModel, RaspberryRevision, RaspberryMemory, RaspberryMaker};
+
+    fn pi(model: RaspberryModel) -> Board {
+        let hardware = Hardware::RaspberryPi(model, RaspberryRevision::R(0), RaspberryMemory(1024), RaspberryMaker::Sony);
+        Board { hardware: hardware, cpu: CPU::BCM2709, overvolted: false }
+    }
+
+    #[test]
+    fn uart() {
+        assert_eq!(pi(RaspberryModel::P2).uart().unwrap(), "/dev/ttyAMA0");
+        assert_eq!(pi(RaspberryModel::P3).uart().unwrap(), "/dev/ttyS0");
+        assert_eq!(pi(RaspberryModel::P0W).uart().unwrap(), "/dev/ttyS0");
+        assert_eq!(pi(RaspberryModel::UN).uart().unwrap(), "/dev/serial0");
+        let unknown = Board { hardware: Hardware::Unknown, cpu: CPU::Unknown, overvolted: false };
+        assert!(unknown.uart().is_err());
+    }
+}
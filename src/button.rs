@@ -0,0 +1,391 @@
+use std::time::Duration;
+use {Result, Logic, DigitalRead, Clock, MonotonicClock};
+
+// Reports a level change only after it has been stable for `stable_time`.
+// Use update() for polled inputs, or feed() with the level from an edge event
+// and settle() once remaining() has elapsed for interrupt-driven ones.
+pub struct Debounced<I, C = MonotonicClock> {
+    input: I,
+    clock: C,
+    stable_time: Duration,
+    level: Option<Logic>,
+    pending: Option<(Logic, Duration)>
+}
+
+impl<I: DigitalRead> Debounced<I> {
+    pub fn new(input: I, stable_time: Duration) -> Debounced<I> {
+        Debounced::with_clock(input, stable_time, MonotonicClock)
+    }
+}
+
+impl<I: DigitalRead, C: Clock> Debounced<I, C> {
+    pub fn with_clock(input: I, stable_time: Duration, clock: C) -> Debounced<I, C> {
+        Debounced {
+            input: input,
+            clock: clock,
+            stable_time: stable_time,
+            level: None,
+            pending: None
+        }
+    }
+
+    pub fn set_stable_time(&mut self, stable_time: Duration) {
+        self.stable_time = stable_time;
+    }
+
+    // Samples the input, returns the new stable level on change
+    pub fn update(&mut self) -> Result<Option<Logic>> {
+        let level = try!(self.input.digital_read());
+        Ok(self.feed(level))
+    }
+
+    pub fn feed(&mut self, level: Logic) -> Option<Logic> {
+        let now = self.clock.now();
+        let stable = match self.level {
+            Some(stable) => stable,
+            None => {
+                // first sample only initializes the state
+                self.level = Some(level);
+                return None;
+            }
+        };
+
+        if level == stable {
+            self.pending = None;
+            return None;
+        }
+
+        match self.pending {
+            Some((pending, _)) if pending == level => (),
+            _ => self.pending = Some((level, now))
+        }
+        self.settle()
+    }
+
+    // Commits the pending level if it has been stable long enough
+    pub fn settle(&mut self) -> Option<Logic> {
+        let now = self.clock.now();
+        match self.pending {
+            Some((level, since)) if now >= since + self.stable_time => {
+                self.level = Some(level);
+                self.pending = None;
+                Some(level)
+            },
+            _ => None
+        }
+    }
+
+    // Time left until the pending level settles
+    pub fn remaining(&self) -> Option<Duration> {
+        self.pending.map(|(_, since)| {
+            let now = self.clock.now();
+            let deadline = since + self.stable_time;
+            if now >= deadline { Duration::new(0, 0) } else { deadline - now }
+        })
+    }
+
+    pub fn level(&self) -> Option<Logic> {
+        self.level
+    }
+
+    pub fn get_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+
+    pub fn into_inner(self) -> I {
+        self.input
+    }
+}
+
+impl<I: DigitalRead, C: Clock> DigitalRead for Debounced<I, C> {
+    fn digital_read(&mut self) -> Result<Logic> {
+        let raw = try!(self.input.digital_read());
+        self.feed(raw);
+        Ok(self.level.unwrap_or(raw))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ButtonEvent {
+    Pressed,
+    Released,
+    Click,
+    DoubleClick,
+    LongPress(Duration)
+}
+
+pub struct Button<I, C = MonotonicClock> {
+    input: Debounced<I, C>,
+    active: Logic,
+    double_click: Duration,
+    long_press: Duration,
+    pressed_at: Option<Duration>,
+    long_reported: bool,
+    second_press: bool,
+    click_at: Option<Duration>
+}
+
+impl<I: DigitalRead> Button<I> {
+    pub fn new(input: I) -> Button<I> {
+        Button::with_clock(input, MonotonicClock)
+    }
+}
+
+impl<I: DigitalRead, C: Clock> Button<I, C> {
+    // Defaults: active low (pull up), 20ms debounce, 300ms double click, 1s long press
+    pub fn with_clock(input: I, clock: C) -> Button<I, C> {
+        Button {
+            input: Debounced::with_clock(input, Duration::from_millis(20), clock),
+            active: Logic::Low,
+            double_click: Duration::from_millis(300),
+            long_press: Duration::from_millis(1000),
+            pressed_at: None,
+            long_reported: false,
+            second_press: false,
+            click_at: None
+        }
+    }
+
+    pub fn active_low(&mut self) -> &mut Self {
+        self.active = Logic::Low; self
+    }
+
+    pub fn active_high(&mut self) -> &mut Self {
+        self.active = Logic::High; self
+    }
+
+    pub fn stable_time(&mut self, stable_time: Duration) -> &mut Self {
+        self.input.set_stable_time(stable_time); self
+    }
+
+    pub fn double_click(&mut self, window: Duration) -> &mut Self {
+        self.double_click = window; self
+    }
+
+    pub fn long_press(&mut self, threshold: Duration) -> &mut Self {
+        self.long_press = threshold; self
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed_at.is_some()
+    }
+
+    // Polled input: sample and advance timers
+    pub fn update(&mut self) -> Result<Vec<ButtonEvent>> {
+        let change = try!(self.input.update());
+        Ok(self.process(change))
+    }
+
+    // Interrupt-driven input: level taken from an edge event
+    pub fn feed(&mut self, level: Logic) -> Vec<ButtonEvent> {
+        let change = self.input.feed(level);
+        self.process(change)
+    }
+
+    // Advances timers without sampling, call when next_deadline() expires
+    pub fn tick(&mut self) -> Vec<ButtonEvent> {
+        let change = self.input.settle();
+        self.process(change)
+    }
+
+    // Time until tick() may produce an event
+    pub fn next_deadline(&self) -> Option<Duration> {
+        let now = self.input.clock.now();
+        let until = |deadline: Duration| if now >= deadline { Duration::new(0, 0) } else { deadline - now };
+
+        let mut next = self.input.remaining();
+        let mut merge = |d: Duration| next = Some(match next { Some(n) if n < d => n, _ => d });
+        if let Some(click_at) = self.click_at {
+            merge(until(click_at + self.double_click));
+        }
+        if let (Some(pressed_at), false) = (self.pressed_at, self.long_reported) {
+            merge(until(pressed_at + self.long_press));
+        }
+        next
+    }
+
+    pub fn get_mut(&mut self) -> &mut I {
+        self.input.get_mut()
+    }
+
+    pub fn into_inner(self) -> I {
+        self.input.into_inner()
+    }
+
+    fn process(&mut self, change: Option<Logic>) -> Vec<ButtonEvent> {
+        let now = self.input.clock.now();
+        let mut events = Vec::new();
+
+        match change {
+            Some(level) if level == self.active => {
+                events.push(ButtonEvent::Pressed);
+                self.pressed_at = Some(now);
+                self.long_reported = false;
+                if let Some(click_at) = self.click_at.take() {
+                    self.second_press = now <= click_at + self.double_click;
+                    if !self.second_press {
+                        events.push(ButtonEvent::Click);
+                    }
+                }
+            },
+            Some(_) => {
+                if self.pressed_at.take().is_some() {
+                    events.push(ButtonEvent::Released);
+                    if self.second_press && !self.long_reported {
+                        events.push(ButtonEvent::DoubleClick);
+                    } else if !self.long_reported {
+                        self.click_at = Some(now);
+                    }
+                    self.second_press = false;
+                }
+            },
+            None => ()
+        }
+
+        if let Some(pressed_at) = self.pressed_at {
+            let held = now - pressed_at;
+            if !self.long_reported && held >= self.long_press {
+                self.long_reported = true;
+                // the second press of a double click became a long press,
+                // the first one still counts as a click
+                if self.second_press {
+                    self.second_press = false;
+                    events.push(ButtonEvent::Click);
+                }
+                events.push(ButtonEvent::LongPress(held));
+            }
+        }
+
+        if let Some(click_at) = self.click_at {
+            if now >= click_at + self.double_click {
+                self.click_at = None;
+                events.push(ButtonEvent::Click);
+            }
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use std::cell::Cell;
+    use std::time::Duration;
+    use {Result, Logic, DigitalRead};
+    use time::FakeClock;
+    use super::{Debounced, Button, ButtonEvent};
+
+    #[derive(Clone)]
+    struct FakePin(Rc<Cell<Logic>>);
+
+    impl DigitalRead for FakePin {
+        fn digital_read(&mut self) -> Result<Logic> {
+            Ok(self.0.get())
+        }
+    }
+
+    fn setup() -> (FakePin, FakeClock) {
+        (FakePin(Rc::new(Cell::new(Logic::High))), FakeClock::new(Duration::from_secs(1)))
+    }
+
+    #[test]
+    fn debounce_ignores_glitch() {
+        let (pin, clock) = setup();
+        let mut input = Debounced::with_clock(pin.clone(), Duration::from_millis(20), clock.clone());
+        assert_eq!(input.update().unwrap(), None);
+
+        pin.0.set(Logic::Low);
+        assert_eq!(input.update().unwrap(), None);
+        clock.advance(5);
+        pin.0.set(Logic::High);
+        assert_eq!(input.update().unwrap(), None);
+        clock.advance(30);
+        assert_eq!(input.update().unwrap(), None);
+        assert_eq!(input.level(), Some(Logic::High));
+
+        pin.0.set(Logic::Low);
+        assert_eq!(input.update().unwrap(), None);
+        clock.advance(20);
+        assert_eq!(input.update().unwrap(), Some(Logic::Low));
+    }
+
+    #[test]
+    fn debounce_settles_fed_level() {
+        let (pin, clock) = setup();
+        let mut input = Debounced::with_clock(pin, Duration::from_millis(20), clock.clone());
+        input.feed(Logic::High);
+        assert_eq!(input.feed(Logic::Low), None);
+        assert_eq!(input.remaining(), Some(Duration::from_millis(20)));
+        clock.advance(20);
+        assert_eq!(input.settle(), Some(Logic::Low));
+        assert_eq!(input.remaining(), None);
+    }
+
+    fn press(button: &mut Button<FakePin, FakeClock>, pin: &FakePin, clock: &FakeClock, ms: u64) -> Vec<ButtonEvent> {
+        let mut events = Vec::new();
+        pin.0.set(Logic::Low);
+        events.extend(button.update().unwrap());
+        clock.advance(20);
+        events.extend(button.update().unwrap());
+        clock.advance(ms);
+        events.extend(button.update().unwrap());
+        pin.0.set(Logic::High);
+        events.extend(button.update().unwrap());
+        clock.advance(20);
+        events.extend(button.update().unwrap());
+        events
+    }
+
+    #[test]
+    fn button_click() {
+        let (pin, clock) = setup();
+        let mut button = Button::with_clock(pin.clone(), clock.clone());
+        button.update().unwrap();
+
+        assert_eq!(press(&mut button, &pin, &clock, 50), vec![ButtonEvent::Pressed, ButtonEvent::Released]);
+        clock.advance(400);
+        assert_eq!(button.tick(), vec![ButtonEvent::Click]);
+    }
+
+    #[test]
+    fn button_double_click() {
+        let (pin, clock) = setup();
+        let mut button = Button::with_clock(pin.clone(), clock.clone());
+        button.update().unwrap();
+
+        press(&mut button, &pin, &clock, 50);
+        clock.advance(100);
+        assert_eq!(press(&mut button, &pin, &clock, 50),
+                   vec![ButtonEvent::Pressed, ButtonEvent::Released, ButtonEvent::DoubleClick]);
+        clock.advance(400);
+        assert_eq!(button.tick(), vec![]);
+    }
+
+    #[test]
+    fn button_long_press() {
+        let (pin, clock) = setup();
+        let mut button = Button::with_clock(pin.clone(), clock.clone());
+        button.update().unwrap();
+
+        assert_eq!(press(&mut button, &pin, &clock, 1500),
+                   vec![ButtonEvent::Pressed, ButtonEvent::LongPress(Duration::from_millis(1500)), ButtonEvent::Released]);
+        clock.advance(400);
+        assert_eq!(button.tick(), vec![]);
+    }
+
+    #[test]
+    fn button_click_then_long_press() {
+        let (pin, clock) = setup();
+        let mut button = Button::with_clock(pin.clone(), clock.clone());
+        button.update().unwrap();
+
+        press(&mut button, &pin, &clock, 50);
+        clock.advance(100);
+        assert_eq!(press(&mut button, &pin, &clock, 1500),
+                   vec![ButtonEvent::Pressed, ButtonEvent::Click,
+                        ButtonEvent::LongPress(Duration::from_millis(1500)), ButtonEvent::Released]);
+        clock.advance(400);
+        assert_eq!(button.tick(), vec![]);
+    }
+}
@@ -0,0 +1,219 @@
+use std::io;
+use {Result, Error};
+
+mod socket;
+mod netlink;
+#[cfg(feature = "tokio")]
+mod stream;
+
+pub use self::socket::CanSocket;
+pub use self::netlink::{LinkInfo, CanState, link_info, set_bitrate, set_link_up};
+
+#[cfg(feature = "tokio")]
+pub use self::stream::{CanStream, WriteFrame};
+
+// can_id flags and masks from linux/can.h
+const CAN_EFF_FLAG: u32 = 0x80000000;
+const CAN_RTR_FLAG: u32 = 0x40000000;
+const CAN_ERR_FLAG: u32 = 0x20000000;
+const CAN_INV_FILTER: u32 = 0x20000000;
+
+pub const CAN_SFF_MASK: u32 = 0x000007FF;
+pub const CAN_EFF_MASK: u32 = 0x1FFFFFFF;
+
+// struct can_frame
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RawFrame {
+    pub can_id: u32,
+    pub can_dlc: u8,
+    pub pad: [u8; 3],
+    pub data: [u8; 8]
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    raw: RawFrame
+}
+
+impl Frame {
+    // 11-bit identifier
+    pub fn standard(id: u32, data: &[u8]) -> Result<Frame> {
+        if id > CAN_SFF_MASK {
+            return Err(Error::InvalidAddress);
+        }
+        Frame::build(id, data)
+    }
+
+    // 29-bit identifier
+    pub fn extended(id: u32, data: &[u8]) -> Result<Frame> {
+        if id > CAN_EFF_MASK {
+            return Err(Error::InvalidAddress);
+        }
+        Frame::build(id | CAN_EFF_FLAG, data)
+    }
+
+    // Remote request for len bytes
+    pub fn remote(id: u32, extended: bool, len: usize) -> Result<Frame> {
+        let mut frame = if extended {
+            try!(Frame::extended(id, &[]))
+        } else {
+            try!(Frame::standard(id, &[]))
+        };
+        if len > 8 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "CAN frames carry up to 8 bytes").into());
+        }
+        frame.raw.can_id |= CAN_RTR_FLAG;
+        frame.raw.can_dlc = len as u8;
+        Ok(frame)
+    }
+
+    fn build(can_id: u32, data: &[u8]) -> Result<Frame> {
+        if data.len() > 8 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "CAN frames carry up to 8 bytes").into());
+        }
+        let mut raw = RawFrame { can_id: can_id, can_dlc: data.len() as u8, pad: [0; 3], data: [0; 8] };
+        raw.data[..data.len()].copy_from_slice(data);
+        Ok(Frame { raw: raw })
+    }
+
+    pub(crate) fn from_raw(raw: RawFrame) -> Frame {
+        Frame { raw: raw }
+    }
+
+    pub(crate) fn raw(&self) -> &RawFrame {
+        &self.raw
+    }
+
+    // Without flags, for error frames the error class bits
+    pub fn id(&self) -> u32 {
+        if self.is_extended() || self.is_error() {
+            self.raw.can_id & CAN_EFF_MASK
+        } else {
+            self.raw.can_id & CAN_SFF_MASK
+        }
+    }
+
+    pub fn is_extended(&self) -> bool {
+        self.raw.can_id & CAN_EFF_FLAG != 0
+    }
+
+    pub fn is_remote(&self) -> bool {
+        self.raw.can_id & CAN_RTR_FLAG != 0
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.raw.can_id & CAN_ERR_FLAG != 0
+    }
+
+    // Requested length for remote frames
+    pub fn len(&self) -> usize {
+        (self.raw.can_dlc as usize).min(8)
+    }
+
+    pub fn data(&self) -> &[u8] {
+        if self.is_remote() {
+            &[]
+        } else {
+            &self.raw.data[..self.len()]
+        }
+    }
+
+    pub fn error(&self) -> Option<CanError> {
+        if !self.is_error() {
+            return None;
+        }
+        Some(CanError {
+            class: ErrorClass::from_bits_truncate(self.raw.can_id & CAN_EFF_MASK),
+            controller: ControllerStatus::from_bits_truncate(self.raw.data[1]),
+            tx_errors: self.raw.data[6],
+            rx_errors: self.raw.data[7]
+        })
+    }
+}
+
+bitflags! {
+    // linux/can/error.h, also the mask for CanSocket::set_error_filter
+    pub struct ErrorClass: u32 {
+        const CAN_ERR_TX_TIMEOUT = 0x001;
+        const CAN_ERR_LOSTARB    = 0x002;
+        const CAN_ERR_CRTL       = 0x004;
+        const CAN_ERR_PROT       = 0x008;
+        const CAN_ERR_TRX        = 0x010;
+        const CAN_ERR_ACK        = 0x020;
+        const CAN_ERR_BUSOFF     = 0x040;
+        const CAN_ERR_BUSERROR   = 0x080;
+        const CAN_ERR_RESTARTED  = 0x100;
+    }
+}
+
+bitflags! {
+    // data[1] of CAN_ERR_CRTL frames
+    pub struct ControllerStatus: u8 {
+        const CAN_ERR_CRTL_RX_OVERFLOW = 0x01;
+        const CAN_ERR_CRTL_TX_OVERFLOW = 0x02;
+        const CAN_ERR_CRTL_RX_WARNING  = 0x04;
+        const CAN_ERR_CRTL_TX_WARNING  = 0x08;
+        const CAN_ERR_CRTL_RX_PASSIVE  = 0x10;
+        const CAN_ERR_CRTL_TX_PASSIVE  = 0x20;
+        const CAN_ERR_CRTL_ACTIVE      = 0x40;
+    }
+}
+
+// Decoded error frame, counters are filled in by some drivers only
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CanError {
+    pub class: ErrorClass,
+    pub controller: ControllerStatus,
+    pub tx_errors: u8,
+    pub rx_errors: u8
+}
+
+// Received when can_id & mask == id & mask
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Filter {
+    id: u32,
+    mask: u32
+}
+
+impl Filter {
+    // Standard frames only
+    pub fn standard(id: u32, mask: u32) -> Filter {
+        Filter { id: id & CAN_SFF_MASK, mask: mask & CAN_SFF_MASK | CAN_EFF_FLAG }
+    }
+
+    // Extended frames only
+    pub fn extended(id: u32, mask: u32) -> Filter {
+        Filter { id: id & CAN_EFF_MASK | CAN_EFF_FLAG, mask: mask & CAN_EFF_MASK | CAN_EFF_FLAG }
+    }
+
+    // Everything the filter would not match
+    pub fn inverted(self) -> Filter {
+        Filter { id: self.id | CAN_INV_FILTER, mask: self.mask }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Frame, ErrorClass, CAN_ERR_FLAG, RawFrame};
+
+    #[test]
+    fn frames() {
+        let frame = Frame::extended(0x18DAF110, &[0x02, 0x10, 0x03]).unwrap();
+        assert!(frame.is_extended() && !frame.is_remote());
+        assert_eq!(frame.id(), 0x18DAF110);
+        assert_eq!(frame.data(), &[0x02, 0x10, 0x03]);
+        assert!(Frame::standard(0x800, &[]).is_err());
+        assert!(Frame::standard(0x7DF, &[0; 9]).is_err());
+
+        let remote = Frame::remote(0x123, false, 4).unwrap();
+        assert!(remote.is_remote());
+        assert_eq!((remote.id(), remote.len(), remote.data().len()), (0x123, 4, 0));
+
+        let raw = RawFrame { can_id: CAN_ERR_FLAG | 0x44, can_dlc: 8, pad: [0; 3], data: [0, 0x20, 0, 0, 0, 0, 130, 7] };
+        let error = Frame::from_raw(raw).error().unwrap();
+        assert_eq!(error.class, ErrorClass::CAN_ERR_CRTL | ErrorClass::CAN_ERR_BUSOFF);
+        assert_eq!((error.tx_errors, error.rx_errors), (130, 7));
+    }
+}
@@ -0,0 +1,287 @@
+use std::io;
+use std::mem;
+use std::fs::File;
+use std::io::prelude::*;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use libc;
+use Result;
+use super::socket::ifindex;
+
+// linux/netlink.h, linux/rtnetlink.h, linux/if_link.h and linux/can/netlink.h
+const NETLINK_ROUTE: libc::c_int = 0;
+const NLM_F_REQUEST: u16 = 0x01;
+const NLM_F_ACK: u16 = 0x04;
+const NLMSG_ERROR: u16 = 0x02;
+const RTM_NEWLINK: u16 = 16;
+const RTM_GETLINK: u16 = 18;
+
+const IFLA_OPERSTATE: u16 = 16;
+const IFLA_LINKINFO: u16 = 18;
+const IFLA_INFO_KIND: u16 = 1;
+const IFLA_INFO_DATA: u16 = 2;
+const IFLA_CAN_BITTIMING: u16 = 1;
+const IFLA_CAN_STATE: u16 = 4;
+const IFLA_CAN_BERR_COUNTER: u16 = 8;
+const NLA_F_NESTED: u16 = 0x8000;
+const NLA_TYPE_MASK: u16 = 0x3FFF;
+
+const IFF_UP: u32 = 0x01;
+const IF_OPER_UP: u8 = 6;
+
+const NLMSG_HDRLEN: usize = 16;
+const IFINFOMSG_LEN: usize = 16;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CanState {
+    ErrorActive,
+    ErrorWarning,
+    ErrorPassive,
+    BusOff,
+    Stopped,
+    Sleeping
+}
+
+impl CanState {
+    fn from_u32(state: u32) -> Option<CanState> {
+        match state {
+            0 => Some(CanState::ErrorActive),
+            1 => Some(CanState::ErrorWarning),
+            2 => Some(CanState::ErrorPassive),
+            3 => Some(CanState::BusOff),
+            4 => Some(CanState::Stopped),
+            5 => Some(CanState::Sleeping),
+            _ => None
+        }
+    }
+}
+
+// CAN fields are None for other links and for drivers without them (vcan)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkInfo {
+    pub up: bool,
+    // carrier, IF_OPER_UP
+    pub running: bool,
+    pub kind: Option<String>,
+    pub bitrate: Option<u32>,
+    // tenths of a percent
+    pub sample_point: Option<u32>,
+    pub state: Option<CanState>,
+    // tx, rx error counters
+    pub error_counters: Option<(u16, u16)>
+}
+
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    let mut b = [0u8; 2];
+    b.copy_from_slice(&buf[at..at + 2]);
+    u16::from_ne_bytes(b)
+}
+
+fn u32_at(buf: &[u8], at: usize) -> u32 {
+    let mut b = [0u8; 4];
+    b.copy_from_slice(&buf[at..at + 4]);
+    u32::from_ne_bytes(b)
+}
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+// (type, payload) of the rtattrs in buf
+fn attributes(buf: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut attrs = Vec::new();
+    let mut at = 0;
+    while at + 4 <= buf.len() {
+        let len = u16_at(buf, at) as usize;
+        if len < 4 || at + len > buf.len() {
+            break;
+        }
+        attrs.push((u16_at(buf, at + 2) & NLA_TYPE_MASK, &buf[at + 4..at + len]));
+        at += align(len);
+    }
+    attrs
+}
+
+fn push_attribute(buf: &mut Vec<u8>, kind: u16, payload: &[u8]) {
+    let len = 4 + payload.len();
+    buf.extend_from_slice(&(len as u16).to_ne_bytes());
+    buf.extend_from_slice(&kind.to_ne_bytes());
+    buf.extend_from_slice(payload);
+    while buf.len() % 4 != 0 {
+        buf.push(0);
+    }
+}
+
+// ifinfomsg followed by its attributes
+fn parse_link(msg: &[u8]) -> io::Result<LinkInfo> {
+    if msg.len() < IFINFOMSG_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "short RTM_NEWLINK"));
+    }
+    let flags = u32_at(msg, 8);
+    let mut info = LinkInfo {
+        up: flags & IFF_UP != 0,
+        running: false,
+        kind: None,
+        bitrate: None,
+        sample_point: None,
+        state: None,
+        error_counters: None
+    };
+    for (kind, payload) in attributes(&msg[IFINFOMSG_LEN..]) {
+        match kind {
+            IFLA_OPERSTATE if payload.len() >= 1 => info.running = payload[0] == IF_OPER_UP,
+            IFLA_LINKINFO => {
+                for (kind, payload) in attributes(payload) {
+                    match kind {
+                        IFLA_INFO_KIND => {
+                            let name = payload.split(|&b| b == 0).next().unwrap_or(&[]);
+                            info.kind = Some(String::from_utf8_lossy(name).into_owned());
+                        },
+                        IFLA_INFO_DATA => {
+                            for (kind, payload) in attributes(payload) {
+                                match kind {
+                                    // struct can_bittiming, bitrate and sample_point first
+                                    IFLA_CAN_BITTIMING if payload.len() >= 8 => {
+                                        info.bitrate = Some(u32_at(payload, 0));
+                                        info.sample_point = Some(u32_at(payload, 4));
+                                    },
+                                    IFLA_CAN_STATE if payload.len() >= 4 => {
+                                        info.state = CanState::from_u32(u32_at(payload, 0));
+                                    },
+                                    IFLA_CAN_BERR_COUNTER if payload.len() >= 4 => {
+                                        info.error_counters = Some((u16_at(payload, 0), u16_at(payload, 2)));
+                                    },
+                                    _ => ()
+                                }
+                            }
+                        },
+                        _ => ()
+                    }
+                }
+            },
+            _ => ()
+        }
+    }
+    Ok(info)
+}
+
+// One request, returns the payload of the first reply (after the nlmsghdr)
+fn request(kind: u16, flags: u16, ifinfo: &[u8], attrs: &[u8]) -> io::Result<Vec<u8>> {
+    let fd = unsafe { libc::socket(libc::AF_NETLINK, libc::SOCK_RAW | libc::SOCK_CLOEXEC, NETLINK_ROUTE) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut socket = unsafe { File::from_raw_fd(fd) };
+
+    let len = NLMSG_HDRLEN + ifinfo.len() + attrs.len();
+    let mut msg = Vec::with_capacity(len);
+    msg.extend_from_slice(&(len as u32).to_ne_bytes());
+    msg.extend_from_slice(&kind.to_ne_bytes());
+    msg.extend_from_slice(&(NLM_F_REQUEST | flags).to_ne_bytes());
+    // sequence number and port id, the kernel fills in ours
+    msg.extend_from_slice(&1u32.to_ne_bytes());
+    msg.extend_from_slice(&0u32.to_ne_bytes());
+    msg.extend_from_slice(ifinfo);
+    msg.extend_from_slice(attrs);
+    try!(socket.write_all(&msg));
+
+    let mut buf = vec![0u8; 16384];
+    let n = unsafe { libc::recv(socket.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let buf = &buf[..n as usize];
+    if buf.len() < NLMSG_HDRLEN || (u32_at(buf, 0) as usize) > buf.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "short netlink reply"));
+    }
+    let payload = &buf[NLMSG_HDRLEN..u32_at(buf, 0) as usize];
+    if u16_at(buf, 4) == NLMSG_ERROR {
+        // negative errno, 0 is the ACK
+        let errno = u32_at(payload, 0) as i32;
+        if errno != 0 {
+            return Err(io::Error::from_raw_os_error(-errno));
+        }
+    }
+    Ok(payload.to_vec())
+}
+
+fn ifinfomsg(index: libc::c_int, flags: u32, change: u32) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(IFINFOMSG_LEN);
+    // ifi_family AF_UNSPEC, padding, ifi_type
+    msg.extend_from_slice(&[0, 0, 0, 0]);
+    msg.extend_from_slice(&index.to_ne_bytes());
+    msg.extend_from_slice(&flags.to_ne_bytes());
+    msg.extend_from_slice(&change.to_ne_bytes());
+    msg
+}
+
+pub fn link_info(interface: &str) -> Result<LinkInfo> {
+    let index = try!(ifindex(interface));
+    let reply = try!(request(RTM_GETLINK, 0, &ifinfomsg(index, 0, 0), &[]));
+    Ok(try!(parse_link(&reply)))
+}
+
+// Needs CAP_NET_ADMIN and the link down
+pub fn set_bitrate(interface: &str, bitrate: u32) -> Result<()> {
+    let index = try!(ifindex(interface));
+    // struct can_bittiming with only the bitrate, the driver calculates the rest
+    let mut bittiming = vec![0u8; 8 * mem::size_of::<u32>()];
+    bittiming[..4].copy_from_slice(&bitrate.to_ne_bytes());
+
+    let mut data = Vec::new();
+    push_attribute(&mut data, IFLA_CAN_BITTIMING, &bittiming);
+    let mut linkinfo = Vec::new();
+    push_attribute(&mut linkinfo, IFLA_INFO_KIND, b"can\0");
+    push_attribute(&mut linkinfo, IFLA_INFO_DATA | NLA_F_NESTED, &data);
+    let mut attrs = Vec::new();
+    push_attribute(&mut attrs, IFLA_LINKINFO | NLA_F_NESTED, &linkinfo);
+
+    try!(request(RTM_NEWLINK, NLM_F_ACK, &ifinfomsg(index, 0, 0), &attrs));
+    Ok(())
+}
+
+// ip link set INTERFACE up/down, needs CAP_NET_ADMIN
+pub fn set_link_up(interface: &str, up: bool) -> Result<()> {
+    let index = try!(ifindex(interface));
+    let flags = if up { IFF_UP } else { 0 };
+    try!(request(RTM_NEWLINK, NLM_F_ACK, &ifinfomsg(index, flags, IFF_UP), &[]));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_link, push_attribute, ifinfomsg, link_info, CanState};
+    use super::{IFLA_OPERSTATE, IFLA_LINKINFO, IFLA_INFO_KIND, IFLA_INFO_DATA, IFLA_CAN_BITTIMING,
+                IFLA_CAN_STATE, IFLA_CAN_BERR_COUNTER, NLA_F_NESTED, IFF_UP};
+
+    #[test]
+    fn can_link_attributes() {
+        let mut bittiming = vec![0u8; 32];
+        bittiming[..4].copy_from_slice(&500_000u32.to_ne_bytes());
+        bittiming[4..8].copy_from_slice(&875u32.to_ne_bytes());
+        let mut data = Vec::new();
+        push_attribute(&mut data, IFLA_CAN_BITTIMING, &bittiming);
+        push_attribute(&mut data, IFLA_CAN_STATE, &2u32.to_ne_bytes());
+        push_attribute(&mut data, IFLA_CAN_BERR_COUNTER, &[128, 0, 3, 0]);
+        let mut linkinfo = Vec::new();
+        push_attribute(&mut linkinfo, IFLA_INFO_KIND, b"can\0");
+        push_attribute(&mut linkinfo, IFLA_INFO_DATA | NLA_F_NESTED, &data);
+
+        let mut msg = ifinfomsg(3, IFF_UP, 0);
+        push_attribute(&mut msg, IFLA_OPERSTATE, &[6]);
+        push_attribute(&mut msg, IFLA_LINKINFO | NLA_F_NESTED, &linkinfo);
+
+        let info = parse_link(&msg).unwrap();
+        assert!(info.up && info.running);
+        assert_eq!(info.kind, Some("can".to_string()));
+        assert_eq!((info.bitrate, info.sample_point), (Some(500_000), Some(875)));
+        assert_eq!(info.state, Some(CanState::ErrorPassive));
+        assert_eq!(info.error_counters, Some((u16::from_ne_bytes([128, 0]), u16::from_ne_bytes([3, 0]))));
+    }
+
+    #[test]
+    fn loopback_link() {
+        let info = link_info("lo").unwrap();
+        assert!(info.up);
+        assert_eq!(info.bitrate, None);
+    }
+}
@@ -0,0 +1,202 @@
+use std::io;
+use std::mem;
+use std::ffi::CString;
+use std::time::Duration;
+use std::fs::File;
+use std::os::unix::io::{RawFd, AsRawFd, FromRawFd};
+use libc;
+use mio::{Token, Evented, Ready, PollOpt, Poll};
+use mio::unix::EventedFd;
+use Result;
+use super::{Frame, RawFrame, Filter, ErrorClass};
+
+// linux/can.h and linux/can/raw.h
+const AF_CAN: libc::c_int = 29;
+const CAN_RAW: libc::c_int = 1;
+const SOL_CAN_RAW: libc::c_int = 101;
+const CAN_RAW_FILTER: libc::c_int = 1;
+const CAN_RAW_ERR_FILTER: libc::c_int = 2;
+const CAN_RAW_LOOPBACK: libc::c_int = 3;
+const CAN_RAW_RECV_OWN_MSGS: libc::c_int = 4;
+
+#[repr(C)]
+struct SockaddrCan {
+    can_family: libc::sa_family_t,
+    can_ifindex: libc::c_int,
+    // transport protocol addresses, unused by CAN_RAW
+    rx_id: u32,
+    tx_id: u32
+}
+
+fn check(res: libc::c_int) -> io::Result<libc::c_int> {
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(res)
+    }
+}
+
+pub(crate) fn ifindex(interface: &str) -> io::Result<libc::c_int> {
+    let name = try!(CString::new(interface).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "bad interface name")));
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(io::Error::new(io::ErrorKind::NotFound, format!("no interface {}", interface))),
+        index => Ok(index as libc::c_int)
+    }
+}
+
+// Raw SocketCAN socket bound to one interface (can0, vcan0).
+// All frames are received until filters are set, error frames are off.
+#[derive(Debug)]
+pub struct CanSocket {
+    file: File
+}
+
+impl CanSocket {
+    pub fn open(interface: &str) -> Result<CanSocket> {
+        let index = try!(ifindex(interface));
+        let fd = try!(check(unsafe { libc::socket(AF_CAN, libc::SOCK_RAW | libc::SOCK_CLOEXEC, CAN_RAW) }));
+        // closes the socket on errors below
+        let socket = CanSocket { file: unsafe { File::from_raw_fd(fd) } };
+
+        let addr = SockaddrCan { can_family: AF_CAN as libc::sa_family_t, can_ifindex: index, rx_id: 0, tx_id: 0 };
+        try!(check(unsafe {
+            libc::bind(fd, &addr as *const _ as *const libc::sockaddr, mem::size_of::<SockaddrCan>() as libc::socklen_t)
+        }));
+        Ok(socket)
+    }
+
+    fn set_option<T>(&self, level: libc::c_int, name: libc::c_int, value: *const T, len: usize) -> io::Result<()> {
+        try!(check(unsafe {
+            libc::setsockopt(self.file.as_raw_fd(), level, name, value as *const libc::c_void, len as libc::socklen_t)
+        }));
+        Ok(())
+    }
+
+    // Frames matching any of the filters, none blocks all data frames
+    pub fn set_filters(&self, filters: &[Filter]) -> Result<()> {
+        Ok(try!(self.set_option(SOL_CAN_RAW, CAN_RAW_FILTER, filters.as_ptr(), filters.len() * mem::size_of::<Filter>())))
+    }
+
+    // Error frames of these classes are received too
+    pub fn set_error_filter(&self, classes: ErrorClass) -> Result<()> {
+        let mask = classes.bits();
+        Ok(try!(self.set_option(SOL_CAN_RAW, CAN_RAW_ERR_FILTER, &mask, mem::size_of::<u32>())))
+    }
+
+    // Other sockets on this host see our frames (default on)
+    pub fn set_loopback(&self, enable: bool) -> Result<()> {
+        let value = enable as libc::c_int;
+        Ok(try!(self.set_option(SOL_CAN_RAW, CAN_RAW_LOOPBACK, &value, mem::size_of::<libc::c_int>())))
+    }
+
+    // This socket sees its own frames (default off)
+    pub fn set_receive_own(&self, enable: bool) -> Result<()> {
+        let value = enable as libc::c_int;
+        Ok(try!(self.set_option(SOL_CAN_RAW, CAN_RAW_RECV_OWN_MSGS, &value, mem::size_of::<libc::c_int>())))
+    }
+
+    // read_frame fails with WouldBlock after the timeout
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        let timeout = timeout.unwrap_or(Duration::from_secs(0));
+        let tv = libc::timeval {
+            tv_sec: timeout.as_secs() as libc::time_t,
+            tv_usec: timeout.subsec_micros() as libc::suseconds_t
+        };
+        Ok(try!(self.set_option(libc::SOL_SOCKET, libc::SO_RCVTIMEO, &tv, mem::size_of::<libc::timeval>())))
+    }
+
+    // Required before registering with mio
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        let fd = self.file.as_raw_fd();
+        let flags = try!(check(unsafe { libc::fcntl(fd, libc::F_GETFL) }));
+        let flags = if nonblocking { flags | libc::O_NONBLOCK } else { flags & !libc::O_NONBLOCK };
+        try!(check(unsafe { libc::fcntl(fd, libc::F_SETFL, flags) }));
+        Ok(())
+    }
+
+    pub fn read_frame(&self) -> io::Result<Frame> {
+        let mut raw = RawFrame { can_id: 0, can_dlc: 0, pad: [0; 3], data: [0; 8] };
+        let size = mem::size_of::<RawFrame>();
+        let n = unsafe { libc::read(self.file.as_raw_fd(), &mut raw as *mut _ as *mut libc::c_void, size) };
+        if n < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if n as usize != size {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "short CAN frame"));
+        }
+        Ok(Frame::from_raw(raw))
+    }
+
+    // ENOBUFS when the interface queue is full, retry later
+    pub fn write_frame(&self, frame: &Frame) -> io::Result<()> {
+        let size = mem::size_of::<RawFrame>();
+        let n = unsafe { libc::write(self.file.as_raw_fd(), frame.raw() as *const _ as *const libc::c_void, size) };
+        if n < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if n as usize != size {
+            return Err(io::Error::new(io::ErrorKind::WriteZero, "short CAN write"));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio")]
+    pub fn into_stream(self) -> Result<super::CanStream> {
+        super::stream::CanStream::new(self)
+    }
+}
+
+impl AsRawFd for CanSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl Evented for CanSocket {
+    fn register(&self, poll: &Poll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.file.as_raw_fd()).register(poll, token, interest, opts)
+    }
+
+    fn reregister(&self, poll: &Poll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.file.as_raw_fd()).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        EventedFd(&self.file.as_raw_fd()).deregister(poll)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use super::super::{Frame, Filter};
+    use super::CanSocket;
+
+    // needs `ip link add dev vcan0 type vcan && ip link set up vcan0`,
+    // run with `cargo test --features can -- --ignored`
+    #[test]
+    #[ignore]
+    fn vcan_filters() {
+        let tx = CanSocket::open("vcan0").unwrap();
+        let rx = CanSocket::open("vcan0").unwrap();
+        rx.set_filters(&[Filter::standard(0x120, 0x7F0), Filter::extended(0x18DAF110, 0x1FFFFFFF)]).unwrap();
+        rx.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+
+        tx.write_frame(&Frame::standard(0x100, &[1]).unwrap()).unwrap();
+        tx.write_frame(&Frame::standard(0x123, &[2, 3]).unwrap()).unwrap();
+        // standard id 0x120 does not match the extended filter and vice versa
+        tx.write_frame(&Frame::extended(0x120, &[4]).unwrap()).unwrap();
+        tx.write_frame(&Frame::extended(0x18DAF110, &[5]).unwrap()).unwrap();
+
+        let frame = rx.read_frame().unwrap();
+        assert_eq!((frame.id(), frame.is_extended(), frame.data()), (0x123, false, &[2, 3][..]));
+        let frame = rx.read_frame().unwrap();
+        assert_eq!((frame.id(), frame.is_extended(), frame.data()), (0x18DAF110, true, &[5][..]));
+        assert!(rx.read_frame().is_err());
+    }
+
+    #[test]
+    fn missing_interface() {
+        assert!(CanSocket::open("nocan7").is_err());
+    }
+}
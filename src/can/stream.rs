@@ -0,0 +1,78 @@
+use std::io;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::unix::AsyncFd;
+use futures_core::Stream;
+use super::{CanSocket, Frame};
+use Result;
+
+// Received frames as a stream, must be created inside a tokio runtime
+pub struct CanStream {
+    fd: AsyncFd<CanSocket>
+}
+
+impl CanStream {
+    pub(crate) fn new(socket: CanSocket) -> Result<CanStream> {
+        try!(socket.set_nonblocking(true));
+        Ok(CanStream { fd: try!(AsyncFd::new(socket)) })
+    }
+
+    pub fn get_ref(&self) -> &CanSocket {
+        self.fd.get_ref()
+    }
+
+    pub fn write_frame<'a>(&'a self, frame: &Frame) -> WriteFrame<'a> {
+        WriteFrame { stream: self, frame: *frame }
+    }
+
+    pub fn poll_read_frame(&self, cx: &mut Context) -> Poll<io::Result<Frame>> {
+        loop {
+            let mut guard = match self.fd.poll_read_ready(cx) {
+                Poll::Ready(Ok(guard)) => guard,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending
+            };
+            match guard.try_io(|fd| fd.get_ref().read_frame()) {
+                Ok(result) => return Poll::Ready(result),
+                Err(_would_block) => continue
+            }
+        }
+    }
+
+    pub fn poll_write_frame(&self, cx: &mut Context, frame: &Frame) -> Poll<io::Result<()>> {
+        loop {
+            let mut guard = match self.fd.poll_write_ready(cx) {
+                Poll::Ready(Ok(guard)) => guard,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending
+            };
+            match guard.try_io(|fd| fd.get_ref().write_frame(frame)) {
+                Ok(result) => return Poll::Ready(result),
+                Err(_would_block) => continue
+            }
+        }
+    }
+}
+
+impl Stream for CanStream {
+    type Item = Result<Frame>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<Frame>>> {
+        self.get_mut().poll_read_frame(cx).map(|result| Some(result.map_err(|err| err.into())))
+    }
+}
+
+pub struct WriteFrame<'a> {
+    stream: &'a CanStream,
+    frame: Frame
+}
+
+impl<'a> Future for WriteFrame<'a> {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        this.stream.poll_write_frame(cx, &this.frame).map(|result| result.map_err(|err| err.into()))
+    }
+}
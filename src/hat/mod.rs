@@ -1,3 +1,4 @@
+#[cfg(feature = "spi")]
 mod piface;
 
 #[cfg(feature = "spi")]
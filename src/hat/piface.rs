@@ -1,15 +1,19 @@
-use mcp23x17::{MCP23S17, PinInput, PinOutput, Port, GroupInput, GroupOutput};
+use std::time::Duration;
+use mcp23x17::{MCP23S17, PinInput, PinOutput, Port, GroupInput, GroupOutput, Interrupts, PinEvent};
+#[cfg(feature = "tokio")]
+use mcp23x17::PinEvents;
 use mio::{Poll, Token};
 use {sys, Result};
 
 // PiFace 2
 #[allow(dead_code)]
 pub struct PiFace {
+    mcp23s17: MCP23S17,
     port_out: Port,
     port_in: Port,
     inputs: GroupInput,
     pin_int: sys::Pin,
-    interrupt: sys::PinInput
+    interrupts: Interrupts
 }
 
 impl PiFace {
@@ -26,13 +30,15 @@ impl PiFace {
         // export interrupt pin
         try!(pin_int.export());
         let interrupt = try!(pin_int.input());
+        let interrupts = Interrupts::new(mcp23s17.interrupt_handler(), interrupt);
 
         Ok(PiFace {
+            mcp23s17: mcp23s17,
             port_out: port_out,
             port_in: port_in,
             inputs: inputs,
             pin_int: pin_int,
-            interrupt: interrupt
+            interrupts: interrupts
         })
     }
 /*
@@ -61,11 +67,35 @@ impl PiFace {
         Ok(try!(self.port_out.group_output(mask)))
     }
 
+    // Call periodically, true when the board was found reset and reconfigured
+    pub fn health_check(&self) -> Result<bool> {
+        self.mcp23s17.health_check()
+    }
+
     pub fn trigger(&mut self, poll: &mut Poll, token: Token) -> Result<()> {
-        Ok(try!(self.interrupt.trigger(poll, token, sys::Edge::FallingEdge)))
+        try!(self.inputs.interrupt(sys::Edge::BothEdges));
+        Ok(try!(self.interrupts.trigger(poll, token)))
     }
 
     pub fn stop_trigger(&mut self, poll: &mut Poll) -> Result<()> {
-        Ok(try!(self.interrupt.stop_trigger(poll)))
+        try!(self.inputs.stop_interrupt());
+        Ok(try!(self.interrupts.stop_trigger(poll)))
+    }
+
+    // Inputs that changed, call on readiness of the trigger token
+    pub fn handle(&mut self) -> Result<Vec<PinEvent>> {
+        self.interrupts.handle()
+    }
+
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Result<Vec<PinEvent>> {
+        try!(self.inputs.interrupt(sys::Edge::BothEdges));
+        self.interrupts.wait(timeout)
+    }
+
+    // Input changes, for use inside tokio::select!
+    #[cfg(feature = "tokio")]
+    pub fn interrupts(&mut self) -> Result<PinEvents> {
+        try!(self.inputs.interrupt(sys::Edge::BothEdges));
+        self.interrupts.events()
     }
 }
@@ -0,0 +1,424 @@
+use std::io;
+use std::time::Duration;
+use std::sync::{Arc, Mutex, MutexGuard};
+use libc;
+use {DigitalRead, DigitalWrite, Logic, Result, Error, delay_usec, monotonic};
+use super::{I2CDevice, I2CBus, Probe};
+
+// Both lines open drain: writing High releases the line (pin input or
+// open drain output with pull-up), reading returns the wire level.
+struct Lines<SCL, SDA> {
+    scl: SCL,
+    sda: SDA,
+    half_period: u64,
+    timeout: Duration
+}
+
+// Software I2C master on two GPIO pins, timed with delay_usec
+pub struct SoftI2C<SCL, SDA> {
+    lines: Arc<Mutex<Lines<SCL, SDA>>>
+}
+
+impl<SCL, SDA> Clone for SoftI2C<SCL, SDA> {
+    fn clone(&self) -> Self {
+        SoftI2C { lines: self.lines.clone() }
+    }
+}
+
+impl<SCL, SDA> SoftI2C<SCL, SDA>
+    where SCL: DigitalRead + DigitalWrite + Send, SDA: DigitalRead + DigitalWrite + Send
+{
+    // 100 kHz, slaves may stretch the clock up to 10 ms
+    pub fn new(scl: SCL, sda: SDA) -> SoftI2C<SCL, SDA> {
+        SoftI2C {
+            lines: Arc::new(Mutex::new(Lines {
+                scl: scl,
+                sda: sda,
+                half_period: 5,
+                timeout: Duration::from_millis(10)
+            }))
+        }
+    }
+
+    pub fn speed(&mut self, hz: u32) -> &mut Self {
+        self.lock().half_period = 500_000 / hz.max(1) as u64;
+        self
+    }
+
+    pub fn stretch_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.lock().timeout = timeout;
+        self
+    }
+
+    // 7-bit address
+    pub fn device(&self, address: u16) -> Result<SoftI2CDevice<SCL, SDA>> {
+        if address > 0x7F {
+            return Err(Error::InvalidAddress);
+        }
+        Ok(SoftI2CDevice { bus: self.clone(), address: address })
+    }
+
+    fn lock(&self) -> MutexGuard<Lines<SCL, SDA>> {
+        match self.lines.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+fn level(bit: bool) -> Logic {
+    if bit { Logic::High } else { Logic::Low }
+}
+
+impl<SCL, SDA> Lines<SCL, SDA>
+    where SCL: DigitalRead + DigitalWrite, SDA: DigitalRead + DigitalWrite
+{
+    fn delay(&self) {
+        delay_usec(self.half_period);
+    }
+
+    // Slaves hold SCL low to stretch the clock
+    fn release_scl(&mut self) -> io::Result<()> {
+        try!(self.scl.digital_write(Logic::High));
+        let start = monotonic();
+        while try!(self.scl.digital_read()) == Logic::Low {
+            if monotonic() - start > self.timeout {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "SCL held low"));
+            }
+        }
+        Ok(())
+    }
+
+    // Also a repeated start when SCL is low
+    fn start(&mut self) -> io::Result<()> {
+        try!(self.sda.digital_write(Logic::High));
+        self.delay();
+        try!(self.release_scl());
+        self.delay();
+        try!(self.sda.digital_write(Logic::Low));
+        self.delay();
+        try!(self.scl.digital_write(Logic::Low));
+        Ok(())
+    }
+
+    fn stop(&mut self) -> io::Result<()> {
+        try!(self.sda.digital_write(Logic::Low));
+        self.delay();
+        try!(self.release_scl());
+        self.delay();
+        try!(self.sda.digital_write(Logic::High));
+        self.delay();
+        Ok(())
+    }
+
+    fn write_bit(&mut self, bit: bool) -> io::Result<()> {
+        try!(self.sda.digital_write(level(bit)));
+        self.delay();
+        try!(self.release_scl());
+        self.delay();
+        try!(self.scl.digital_write(Logic::Low));
+        Ok(())
+    }
+
+    fn read_bit(&mut self) -> io::Result<bool> {
+        try!(self.sda.digital_write(Logic::High));
+        self.delay();
+        try!(self.release_scl());
+        self.delay();
+        let bit = try!(self.sda.digital_read()) == Logic::High;
+        try!(self.scl.digital_write(Logic::Low));
+        Ok(bit)
+    }
+
+    // true when the slave acknowledged
+    fn write_byte(&mut self, byte: u8) -> io::Result<bool> {
+        for n in (0..8).rev() {
+            try!(self.write_bit(byte & (1 << n) != 0));
+        }
+        Ok(!try!(self.read_bit()))
+    }
+
+    // the last byte of a read is not acknowledged
+    fn read_byte(&mut self, ack: bool) -> io::Result<u8> {
+        let mut byte = 0u8;
+        for _ in 0..8 {
+            byte = byte << 1 | try!(self.read_bit()) as u8;
+        }
+        try!(self.write_bit(!ack));
+        Ok(byte)
+    }
+
+    fn messages(&mut self, address: u16, data: Option<&[u8]>, buf: Option<&mut [u8]>) -> io::Result<()> {
+        let address = (address << 1) as u8;
+        if let Some(data) = data {
+            try!(self.start());
+            if !try!(self.write_byte(address)) {
+                return Err(io::Error::from_raw_os_error(libc::ENXIO));
+            }
+            for &byte in data {
+                if !try!(self.write_byte(byte)) {
+                    return Err(io::Error::from_raw_os_error(libc::EIO));
+                }
+            }
+        }
+        if let Some(buf) = buf {
+            try!(self.start());
+            if !try!(self.write_byte(address | 1)) {
+                return Err(io::Error::from_raw_os_error(libc::ENXIO));
+            }
+            let len = buf.len();
+            for (i, byte) in buf.iter_mut().enumerate() {
+                *byte = try!(self.read_byte(i + 1 < len));
+            }
+        }
+        Ok(())
+    }
+
+    // Write and/or read with a repeated start, a NACK still ends with a stop
+    fn transfer(&mut self, address: u16, data: Option<&[u8]>, buf: Option<&mut [u8]>) -> io::Result<()> {
+        match self.messages(address, data, buf) {
+            Ok(()) => self.stop(),
+            Err(err) => {
+                let _ = self.stop();
+                Err(err)
+            }
+        }
+    }
+}
+
+impl<SCL, SDA> I2CBus for SoftI2C<SCL, SDA>
+    where SCL: DigitalRead + DigitalWrite + Send, SDA: DigitalRead + DigitalWrite + Send
+{
+    // Address only write, the slave acks or not
+    fn probe(&mut self, address: u16) -> io::Result<Probe> {
+        if address > 0x7F {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a 7-bit address"));
+        }
+        match self.lock().transfer(address, Some(&[]), None) {
+            Ok(()) => Ok(Probe::Present),
+            Err(ref err) if err.raw_os_error() == Some(libc::ENXIO) => Ok(Probe::Empty),
+            Err(err) => Err(err)
+        }
+    }
+}
+
+pub struct SoftI2CDevice<SCL, SDA> {
+    bus: SoftI2C<SCL, SDA>,
+    address: u16
+}
+
+impl<SCL, SDA> I2CDevice for SoftI2CDevice<SCL, SDA>
+    where SCL: DigitalRead + DigitalWrite + Send, SDA: DigitalRead + DigitalWrite + Send
+{
+    fn address(&self) -> u16 {
+        self.address
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.bus.lock().transfer(self.address, Some(data), None)
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.bus.lock().transfer(self.address, None, Some(buf))
+    }
+
+    fn write_read(&mut self, data: &[u8], buf: &mut [u8]) -> io::Result<()> {
+        self.bus.lock().transfer(self.address, Some(data), Some(buf))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use std::time::Duration;
+    use std::sync::{Arc, Mutex};
+    use {DigitalRead, DigitalWrite, DigitalLogic, Logic, Result};
+    use super::super::{I2CDevice, I2CBus, Probe};
+    use super::SoftI2C;
+
+    #[derive(PartialEq)]
+    enum State { Idle, Receive, Transmit }
+
+    // Open drain wire with a register pointer slave on it, decoded from
+    // the line transitions the master makes
+    struct Wire {
+        scl: bool,
+        sda: bool,
+        slave_sda: bool,
+        stretch: usize,
+        address: u8,
+        regs: [u8; 256],
+        ptr: u8,
+        state: State,
+        bit: u8,
+        byte: u8,
+        addressed: bool,
+        reading: bool,
+        first: bool
+    }
+
+    impl Wire {
+        fn sda_line(&self) -> bool {
+            self.sda && self.slave_sda
+        }
+
+        fn set(&mut self, scl: bool, sda: bool) {
+            let (old_scl, old_sda) = (self.scl, self.sda_line());
+            self.scl = scl;
+            self.sda = sda;
+            let sda = self.sda_line();
+            if old_scl && scl && old_sda != sda {
+                // START or STOP
+                self.slave_sda = true;
+                self.state = if sda { State::Idle } else { State::Receive };
+                self.bit = 0;
+                self.addressed = false;
+            } else if !old_scl && scl {
+                self.rising(sda);
+            } else if old_scl && !scl {
+                self.falling();
+            }
+        }
+
+        fn rising(&mut self, sda: bool) {
+            match self.state {
+                State::Receive if self.bit < 8 => {
+                    self.byte = self.byte << 1 | sda as u8;
+                    self.bit += 1;
+                }
+                State::Receive => self.bit += 1,
+                State::Transmit => {
+                    self.bit += 1;
+                    // master NACK ends the read
+                    if self.bit == 9 && sda {
+                        self.state = State::Idle;
+                    }
+                }
+                State::Idle => ()
+            }
+        }
+
+        fn falling(&mut self) {
+            match self.state {
+                State::Receive if self.bit == 8 => {
+                    let ack = if !self.addressed {
+                        self.reading = self.byte & 1 != 0;
+                        self.first = true;
+                        self.byte >> 1 == self.address
+                    } else if self.first {
+                        self.ptr = self.byte;
+                        self.first = false;
+                        true
+                    } else {
+                        self.regs[self.ptr as usize] = self.byte;
+                        self.ptr = self.ptr.wrapping_add(1);
+                        true
+                    };
+                    if ack {
+                        self.addressed = true;
+                        self.slave_sda = false;
+                    } else {
+                        self.state = State::Idle;
+                    }
+                }
+                State::Receive if self.bit == 9 => {
+                    self.slave_sda = true;
+                    self.bit = 0;
+                    if self.reading {
+                        self.state = State::Transmit;
+                        self.bit = 9;
+                        self.falling();
+                    }
+                }
+                State::Transmit => {
+                    if self.bit == 9 {
+                        self.byte = self.regs[self.ptr as usize];
+                        self.ptr = self.ptr.wrapping_add(1);
+                        self.bit = 0;
+                    }
+                    self.slave_sda = self.bit == 8 || self.byte & (0x80 >> self.bit) != 0;
+                }
+                _ => ()
+            }
+        }
+    }
+
+    struct Scl(Arc<Mutex<Wire>>);
+    struct Sda(Arc<Mutex<Wire>>);
+
+    impl DigitalWrite for Scl {
+        fn digital_write<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+            let mut wire = self.0.lock().unwrap();
+            let sda = wire.sda;
+            wire.set(level.logic_level() == Logic::High, sda);
+            Ok(())
+        }
+    }
+
+    impl DigitalRead for Scl {
+        fn digital_read(&mut self) -> Result<Logic> {
+            let mut wire = self.0.lock().unwrap();
+            if wire.stretch > 0 {
+                wire.stretch -= 1;
+                return Ok(Logic::Low);
+            }
+            Ok(if wire.scl { Logic::High } else { Logic::Low })
+        }
+    }
+
+    impl DigitalWrite for Sda {
+        fn digital_write<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+            let mut wire = self.0.lock().unwrap();
+            let scl = wire.scl;
+            wire.set(scl, level.logic_level() == Logic::High);
+            Ok(())
+        }
+    }
+
+    impl DigitalRead for Sda {
+        fn digital_read(&mut self) -> Result<Logic> {
+            Ok(if self.0.lock().unwrap().sda_line() { Logic::High } else { Logic::Low })
+        }
+    }
+
+    fn bus(address: u8) -> (Arc<Mutex<Wire>>, SoftI2C<Scl, Sda>) {
+        let wire = Arc::new(Mutex::new(Wire {
+            scl: true, sda: true, slave_sda: true, stretch: 0,
+            address: address, regs: [0; 256], ptr: 0,
+            state: State::Idle, bit: 0, byte: 0,
+            addressed: false, reading: false, first: false
+        }));
+        let mut bus = SoftI2C::new(Scl(wire.clone()), Sda(wire.clone()));
+        bus.speed(1_000_000);
+        (wire, bus)
+    }
+
+    #[test]
+    fn register_access() {
+        let (wire, mut bus) = bus(0x48);
+        let mut dev = bus.device(0x48).unwrap();
+        dev.write(&[0x10, 0xA5, 0x3C]).unwrap();
+        assert_eq!(&wire.lock().unwrap().regs[0x10..0x12], &[0xA5, 0x3C]);
+
+        // slow slave, repeated start between write and read
+        wire.lock().unwrap().stretch = 100;
+        let mut buf = [0u8; 2];
+        dev.write_read(&[0x10], &mut buf).unwrap();
+        assert_eq!(buf, [0xA5, 0x3C]);
+        assert_eq!(dev.smbus_read_word_data(0x10).unwrap(), 0x3CA5);
+
+        assert_eq!(bus.probe(0x48).unwrap(), Probe::Present);
+        assert_eq!(bus.probe(0x49).unwrap(), Probe::Empty);
+        let err = bus.device(0x20).unwrap().write(&[0]).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(::libc::ENXIO));
+    }
+
+    #[test]
+    fn clock_stretch_timeout() {
+        let (wire, mut bus) = bus(0x48);
+        bus.stretch_timeout(Duration::from_millis(1));
+        wire.lock().unwrap().stretch = usize::max_value();
+        let err = bus.device(0x48).unwrap().write(&[0]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}
@@ -0,0 +1,222 @@
+use std::io;
+use std::io::prelude::*;
+use std::fs::{OpenOptions, File};
+use std::os::unix::io::AsRawFd;
+use libc;
+use {Result, Error};
+use super::{I2CDevice, I2CBus, Probe, SMBUS_BLOCK_MAX};
+
+// ioctls and structs from linux/i2c-dev.h and linux/i2c.h
+const I2C_SLAVE: libc::c_ulong = 0x0703;
+const I2C_TENBIT: libc::c_ulong = 0x0704;
+const I2C_RDWR: libc::c_ulong = 0x0707;
+const I2C_SMBUS: libc::c_ulong = 0x0720;
+
+const I2C_M_RD: u16 = 0x0001;
+const I2C_M_TEN: u16 = 0x0010;
+
+const I2C_SMBUS_WRITE: u8 = 0;
+const I2C_SMBUS_READ: u8 = 1;
+
+const I2C_SMBUS_QUICK: u32 = 0;
+const I2C_SMBUS_BYTE: u32 = 1;
+const I2C_SMBUS_BYTE_DATA: u32 = 2;
+const I2C_SMBUS_WORD_DATA: u32 = 3;
+const I2C_SMBUS_BLOCK_DATA: u32 = 5;
+
+#[repr(C)]
+struct I2CMsg {
+    addr: u16,
+    flags: u16,
+    len: u16,
+    buf: *mut u8
+}
+
+#[repr(C)]
+struct I2CRdwrData {
+    msgs: *mut I2CMsg,
+    nmsgs: u32
+}
+
+#[repr(C)]
+struct I2CSmbusData {
+    read_write: u8,
+    command: u8,
+    size: u32,
+    // union i2c_smbus_data: byte, word or count + block + PEC
+    data: *mut [u8; SMBUS_BLOCK_MAX + 2]
+}
+
+fn ioctl(file: &File, request: libc::c_ulong, arg: libc::c_ulong) -> io::Result<()> {
+    if unsafe { libc::ioctl(file.as_raw_fd(), request, arg) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn smbus(file: &File, read_write: u8, command: u8, size: u32, data: &mut [u8; SMBUS_BLOCK_MAX + 2]) -> io::Result<()> {
+    let mut args = I2CSmbusData {
+        read_write: read_write,
+        command: command,
+        size: size,
+        data: data
+    };
+    ioctl(file, I2C_SMBUS, &mut args as *mut _ as libc::c_ulong)
+}
+
+// Character device /dev/i2c-N bound to one slave address
+#[derive(Debug)]
+pub struct LinuxI2CDevice {
+    file: File,
+    address: u16,
+    tenbit: bool
+}
+
+impl LinuxI2CDevice {
+    // 7-bit address
+    pub fn open(bus: usize, address: u16) -> Result<LinuxI2CDevice> {
+        if address > 0x7F {
+            return Err(Error::InvalidAddress);
+        }
+        LinuxI2CDevice::open_path(format!("/dev/i2c-{}", bus), address, false)
+    }
+
+    pub fn open_10bit(bus: usize, address: u16) -> Result<LinuxI2CDevice> {
+        if address > 0x3FF {
+            return Err(Error::InvalidAddress);
+        }
+        LinuxI2CDevice::open_path(format!("/dev/i2c-{}", bus), address, true)
+    }
+
+    fn open_path(path: String, address: u16, tenbit: bool) -> Result<LinuxI2CDevice> {
+        let file = try!(OpenOptions::new().read(true).write(true).open(path));
+        try!(ioctl(&file, I2C_TENBIT, tenbit as libc::c_ulong));
+        try!(ioctl(&file, I2C_SLAVE, address as libc::c_ulong));
+        Ok(LinuxI2CDevice { file: file, address: address, tenbit: tenbit })
+    }
+
+
+    fn msg(&self, flags: u16, buf: *mut u8, len: usize) -> I2CMsg {
+        let ten = if self.tenbit { I2C_M_TEN } else { 0 };
+        I2CMsg { addr: self.address, flags: flags | ten, len: len as u16, buf: buf }
+    }
+
+    fn smbus(&mut self, read_write: u8, command: u8, size: u32, data: &mut [u8; SMBUS_BLOCK_MAX + 2]) -> io::Result<()> {
+        smbus(&self.file, read_write, command, size, data)
+    }
+}
+
+impl I2CDevice for LinuxI2CDevice {
+    fn address(&self) -> u16 {
+        self.address
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.file.write_all(data)
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.file.read_exact(buf)
+    }
+
+    // One I2C_RDWR transaction
+    fn write_read(&mut self, data: &[u8], buf: &mut [u8]) -> io::Result<()> {
+        let mut msgs = [
+            self.msg(0, data.as_ptr() as *mut u8, data.len()),
+            self.msg(I2C_M_RD, buf.as_mut_ptr(), buf.len())
+        ];
+        let mut args = I2CRdwrData { msgs: msgs.as_mut_ptr(), nmsgs: msgs.len() as u32 };
+        ioctl(&self.file, I2C_RDWR, &mut args as *mut _ as libc::c_ulong)
+    }
+
+    fn smbus_write_quick(&mut self, bit: bool) -> io::Result<()> {
+        let mut data = [0u8; SMBUS_BLOCK_MAX + 2];
+        self.smbus(bit as u8, 0, I2C_SMBUS_QUICK, &mut data)
+    }
+
+    fn smbus_read_byte(&mut self) -> io::Result<u8> {
+        let mut data = [0u8; SMBUS_BLOCK_MAX + 2];
+        try!(self.smbus(I2C_SMBUS_READ, 0, I2C_SMBUS_BYTE, &mut data));
+        Ok(data[0])
+    }
+
+    fn smbus_write_byte(&mut self, value: u8) -> io::Result<()> {
+        let mut data = [0u8; SMBUS_BLOCK_MAX + 2];
+        // the byte goes in the command field
+        self.smbus(I2C_SMBUS_WRITE, value, I2C_SMBUS_BYTE, &mut data)
+    }
+
+    fn smbus_read_byte_data(&mut self, command: u8) -> io::Result<u8> {
+        let mut data = [0u8; SMBUS_BLOCK_MAX + 2];
+        try!(self.smbus(I2C_SMBUS_READ, command, I2C_SMBUS_BYTE_DATA, &mut data));
+        Ok(data[0])
+    }
+
+    fn smbus_write_byte_data(&mut self, command: u8, value: u8) -> io::Result<()> {
+        let mut data = [0u8; SMBUS_BLOCK_MAX + 2];
+        data[0] = value;
+        self.smbus(I2C_SMBUS_WRITE, command, I2C_SMBUS_BYTE_DATA, &mut data)
+    }
+
+    fn smbus_read_word_data(&mut self, command: u8) -> io::Result<u16> {
+        let mut data = [0u8; SMBUS_BLOCK_MAX + 2];
+        try!(self.smbus(I2C_SMBUS_READ, command, I2C_SMBUS_WORD_DATA, &mut data));
+        Ok(data[0] as u16 | (data[1] as u16) << 8)
+    }
+
+    fn smbus_write_word_data(&mut self, command: u8, value: u16) -> io::Result<()> {
+        let mut data = [0u8; SMBUS_BLOCK_MAX + 2];
+        data[0] = value as u8;
+        data[1] = (value >> 8) as u8;
+        self.smbus(I2C_SMBUS_WRITE, command, I2C_SMBUS_WORD_DATA, &mut data)
+    }
+
+    fn smbus_read_block_data(&mut self, command: u8) -> io::Result<Vec<u8>> {
+        let mut data = [0u8; SMBUS_BLOCK_MAX + 2];
+        try!(self.smbus(I2C_SMBUS_READ, command, I2C_SMBUS_BLOCK_DATA, &mut data));
+        let count = (data[0] as usize).min(SMBUS_BLOCK_MAX);
+        Ok(data[1..count + 1].to_vec())
+    }
+
+    fn smbus_write_block_data(&mut self, command: u8, values: &[u8]) -> io::Result<()> {
+        if values.len() > SMBUS_BLOCK_MAX {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "SMBus block too long"));
+        }
+        let mut data = [0u8; SMBUS_BLOCK_MAX + 2];
+        data[0] = values.len() as u8;
+        data[1..values.len() + 1].copy_from_slice(values);
+        self.smbus(I2C_SMBUS_WRITE, command, I2C_SMBUS_BLOCK_DATA, &mut data)
+    }
+}
+
+// Whole /dev/i2c-N adapter, for scanning
+#[derive(Debug)]
+pub struct LinuxI2CBus {
+    file: File
+}
+
+impl LinuxI2CBus {
+    pub fn open(bus: usize) -> Result<LinuxI2CBus> {
+        let file = try!(OpenOptions::new().read(true).write(true).open(format!("/dev/i2c-{}", bus)));
+        Ok(LinuxI2CBus { file: file })
+    }
+}
+
+impl I2CBus for LinuxI2CBus {
+    // Same probes as i2cdetect: a read byte where a quick write could
+    // corrupt an EEPROM or lock a write-only chip, a quick write elsewhere
+    fn probe(&mut self, address: u16) -> io::Result<Probe> {
+        if let Err(err) = ioctl(&self.file, I2C_SLAVE, address as libc::c_ulong) {
+            if err.raw_os_error() == Some(libc::EBUSY) {
+                return Ok(Probe::Busy);
+            }
+            return Err(err);
+        }
+        let mut data = [0u8; SMBUS_BLOCK_MAX + 2];
+        let ack = match address {
+            0x30..=0x37 | 0x50..=0x5F => smbus(&self.file, I2C_SMBUS_READ, 0, I2C_SMBUS_BYTE, &mut data),
+            _ => smbus(&self.file, I2C_SMBUS_WRITE, 0, I2C_SMBUS_QUICK, &mut data)
+        };
+        Ok(if ack.is_ok() { Probe::Present } else { Probe::Empty })
+    }
+}
@@ -0,0 +1,145 @@
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use libc;
+use super::{I2CDevice, I2CBus, Probe};
+
+struct State {
+    address: u16,
+    regs: [u8; 256],
+    pointer: u8,
+    connected: bool,
+    transactions: usize
+}
+
+// In-memory slave with 256 byte registers and an auto-incrementing
+// register pointer set by the first byte written, like most sensors.
+// Clones share the device, so a test can keep one to inspect it.
+#[derive(Clone)]
+pub struct MockI2CDevice(Arc<Mutex<State>>);
+
+impl MockI2CDevice {
+    pub fn new(address: u16) -> MockI2CDevice {
+        MockI2CDevice(Arc::new(Mutex::new(State {
+            address: address,
+            regs: [0; 256],
+            pointer: 0,
+            connected: true,
+            transactions: 0
+        })))
+    }
+
+    fn state(&self) -> MutexGuard<State> {
+        match self.0.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    pub fn register(&self, reg: u8) -> u8 {
+        self.state().regs[reg as usize]
+    }
+
+    pub fn set_register(&self, reg: u8, value: u8) {
+        self.state().regs[reg as usize] = value;
+    }
+
+    // Disconnected device does not ack, transfers fail with ENXIO
+    pub fn set_connected(&self, connected: bool) {
+        self.state().connected = connected;
+    }
+
+    // Number of transfers, a write_read counts once
+    pub fn transactions(&self) -> usize {
+        self.state().transactions
+    }
+}
+
+impl State {
+    fn start(&mut self) -> io::Result<()> {
+        self.transactions += 1;
+        if !self.connected {
+            return Err(io::Error::from_raw_os_error(libc::ENXIO));
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) {
+        if let Some((&pointer, values)) = data.split_first() {
+            self.pointer = pointer;
+            for &value in values {
+                self.regs[self.pointer as usize] = value;
+                self.pointer = self.pointer.wrapping_add(1);
+            }
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) {
+        for byte in buf.iter_mut() {
+            *byte = self.regs[self.pointer as usize];
+            self.pointer = self.pointer.wrapping_add(1);
+        }
+    }
+}
+
+impl I2CDevice for MockI2CDevice {
+    fn address(&self) -> u16 {
+        self.state().address
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut state = self.state();
+        try!(state.start());
+        state.write(data);
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let mut state = self.state();
+        try!(state.start());
+        state.read(buf);
+        Ok(())
+    }
+
+    fn write_read(&mut self, data: &[u8], buf: &mut [u8]) -> io::Result<()> {
+        let mut state = self.state();
+        try!(state.start());
+        state.write(data);
+        state.read(buf);
+        Ok(())
+    }
+}
+
+// Bus with mock devices and addresses claimed by kernel drivers
+#[derive(Clone, Default)]
+pub struct MockI2CBus {
+    devices: Vec<MockI2CDevice>,
+    claimed: Vec<u16>
+}
+
+impl MockI2CBus {
+    pub fn new() -> MockI2CBus {
+        MockI2CBus::default()
+    }
+
+    pub fn add(&mut self, device: MockI2CDevice) -> &mut Self {
+        self.devices.push(device); self
+    }
+
+    pub fn claim(&mut self, address: u16) -> &mut Self {
+        self.claimed.push(address); self
+    }
+}
+
+impl I2CBus for MockI2CBus {
+    fn probe(&mut self, address: u16) -> io::Result<Probe> {
+        if self.claimed.contains(&address) {
+            return Ok(Probe::Busy);
+        }
+        for dev in self.devices.iter_mut().filter(|dev| dev.address() == address) {
+            if dev.smbus_write_quick(false).is_ok() {
+                return Ok(Probe::Present);
+            }
+        }
+        Ok(Probe::Empty)
+    }
+}
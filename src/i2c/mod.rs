@@ -0,0 +1,133 @@
+use std::io;
+
+mod linux;
+mod mock;
+mod scan;
+mod bitbang;
+
+pub use self::linux::{LinuxI2CDevice, LinuxI2CBus};
+pub use self::mock::{MockI2CDevice, MockI2CBus};
+pub use self::scan::{scan, scan_range, identify, Found, Chip, CHIPS};
+pub use self::bitbang::{SoftI2C, SoftI2CDevice};
+
+// Largest SMBus block transfer
+pub const SMBUS_BLOCK_MAX: usize = 32;
+
+// One slave on an I2C bus. SMBus commands default to plain I2C transfers,
+// which is what the bus sees for them, so an implementation only needs
+// write, read and write_read.
+pub trait I2CDevice: Send {
+    fn address(&self) -> u16;
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()>;
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<()>;
+
+    // Write then read with a repeated start, no stop in between
+    fn write_read(&mut self, data: &[u8], buf: &mut [u8]) -> io::Result<()>;
+
+    fn smbus_write_quick(&mut self, bit: bool) -> io::Result<()> {
+        if bit {
+            self.read(&mut [])
+        } else {
+            self.write(&[])
+        }
+    }
+
+    fn smbus_read_byte(&mut self) -> io::Result<u8> {
+        let mut buf = [0u8];
+        try!(self.read(&mut buf));
+        Ok(buf[0])
+    }
+
+    fn smbus_write_byte(&mut self, value: u8) -> io::Result<()> {
+        self.write(&[value])
+    }
+
+    fn smbus_read_byte_data(&mut self, command: u8) -> io::Result<u8> {
+        let mut buf = [0u8];
+        try!(self.write_read(&[command], &mut buf));
+        Ok(buf[0])
+    }
+
+    fn smbus_write_byte_data(&mut self, command: u8, value: u8) -> io::Result<()> {
+        self.write(&[command, value])
+    }
+
+    // SMBus words are little-endian
+    fn smbus_read_word_data(&mut self, command: u8) -> io::Result<u16> {
+        let mut buf = [0u8; 2];
+        try!(self.write_read(&[command], &mut buf));
+        Ok(buf[0] as u16 | (buf[1] as u16) << 8)
+    }
+
+    fn smbus_write_word_data(&mut self, command: u8, value: u16) -> io::Result<()> {
+        self.write(&[command, value as u8, (value >> 8) as u8])
+    }
+
+    // Count byte first, then up to 32 data bytes
+    fn smbus_read_block_data(&mut self, command: u8) -> io::Result<Vec<u8>> {
+        let mut buf = [0u8; SMBUS_BLOCK_MAX + 1];
+        try!(self.write_read(&[command], &mut buf));
+        let count = buf[0] as usize;
+        if count > SMBUS_BLOCK_MAX {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "SMBus block too long"));
+        }
+        Ok(buf[1..count + 1].to_vec())
+    }
+
+    fn smbus_write_block_data(&mut self, command: u8, data: &[u8]) -> io::Result<()> {
+        if data.len() > SMBUS_BLOCK_MAX {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "SMBus block too long"));
+        }
+        let mut tx = Vec::with_capacity(data.len() + 2);
+        tx.push(command);
+        tx.push(data.len() as u8);
+        tx.extend_from_slice(data);
+        self.write(&tx)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Probe {
+    Empty,
+    Present,
+    // claimed by a kernel driver
+    Busy
+}
+
+// Adapter level access, any address
+pub trait I2CBus {
+    fn probe(&mut self, address: u16) -> io::Result<Probe>;
+}
+
+#[cfg(test)]
+mod test {
+    use super::{I2CDevice, MockI2CDevice};
+
+    #[test]
+    fn smbus_over_plain_transfers() {
+        let mock = MockI2CDevice::new(0x48);
+        let mut dev = mock.clone();
+
+        dev.smbus_write_byte_data(0x01, 0x60).unwrap();
+        assert_eq!(mock.register(0x01), 0x60);
+        assert_eq!(dev.smbus_read_byte_data(0x01).unwrap(), 0x60);
+
+        dev.smbus_write_word_data(0x02, 0x1234).unwrap();
+        assert_eq!((mock.register(0x02), mock.register(0x03)), (0x34, 0x12));
+        assert_eq!(dev.smbus_read_word_data(0x02).unwrap(), 0x1234);
+
+        dev.smbus_write_block_data(0x10, &[1, 2, 3]).unwrap();
+        assert_eq!(dev.smbus_read_block_data(0x10).unwrap(), vec![1, 2, 3]);
+
+        let before = mock.transactions();
+        let mut buf = [0u8; 2];
+        dev.write_read(&[0x02], &mut buf).unwrap();
+        assert_eq!(buf, [0x34, 0x12]);
+        assert_eq!(mock.transactions(), before + 1);
+
+        mock.set_connected(false);
+        assert!(dev.smbus_read_byte_data(0x01).is_err());
+    }
+}
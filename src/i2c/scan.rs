@@ -0,0 +1,110 @@
+use Result;
+use super::{I2CBus, Probe};
+
+#[derive(Debug)]
+pub struct Chip {
+    pub name: &'static str,
+    pub description: &'static str,
+    // inclusive address ranges the chip can be strapped to
+    pub addresses: &'static [(u16, u16)]
+}
+
+impl Chip {
+    pub fn responds_at(&self, address: u16) -> bool {
+        self.addresses.iter().any(|&(first, last)| address >= first && address <= last)
+    }
+}
+
+// Common breakout board chips. Many share addresses, so a match is a hint only.
+pub static CHIPS: &'static [Chip] = &[
+    Chip { name: "MCP23017", description: "16-bit I/O expander", addresses: &[(0x20, 0x27)] },
+    Chip { name: "MCP23008", description: "8-bit I/O expander", addresses: &[(0x20, 0x27)] },
+    Chip { name: "PCF8574", description: "8-bit I/O expander", addresses: &[(0x20, 0x27)] },
+    Chip { name: "PCF8574A", description: "8-bit I/O expander", addresses: &[(0x38, 0x3F)] },
+    Chip { name: "PCF8575", description: "16-bit I/O expander", addresses: &[(0x20, 0x27)] },
+    Chip { name: "BH1750", description: "light sensor", addresses: &[(0x23, 0x23), (0x5C, 0x5C)] },
+    Chip { name: "TSL2561", description: "light sensor", addresses: &[(0x29, 0x29), (0x39, 0x39), (0x49, 0x49)] },
+    Chip { name: "SSD1306", description: "OLED display", addresses: &[(0x3C, 0x3D)] },
+    // default address and the all-call address, the other straps overlap too much
+    Chip { name: "PCA9685", description: "16-channel PWM", addresses: &[(0x40, 0x40), (0x70, 0x70)] },
+    Chip { name: "HTU21D", description: "humidity sensor", addresses: &[(0x40, 0x40)] },
+    Chip { name: "INA219", description: "current sensor", addresses: &[(0x40, 0x4F)] },
+    Chip { name: "SHT31", description: "humidity sensor", addresses: &[(0x44, 0x45)] },
+    Chip { name: "ADS1115", description: "16-bit ADC", addresses: &[(0x48, 0x4B)] },
+    Chip { name: "ADS1015", description: "12-bit ADC", addresses: &[(0x48, 0x4B)] },
+    Chip { name: "LM75", description: "temperature sensor", addresses: &[(0x48, 0x4F)] },
+    Chip { name: "AT24C32", description: "EEPROM", addresses: &[(0x50, 0x57)] },
+    Chip { name: "CCS811", description: "air quality sensor", addresses: &[(0x5A, 0x5B)] },
+    Chip { name: "DS3231", description: "real-time clock", addresses: &[(0x68, 0x68)] },
+    Chip { name: "DS1307", description: "real-time clock", addresses: &[(0x68, 0x68)] },
+    Chip { name: "MPU6050", description: "accelerometer/gyroscope", addresses: &[(0x68, 0x69)] },
+    Chip { name: "TCA9548A", description: "I2C multiplexer", addresses: &[(0x70, 0x77)] },
+    Chip { name: "BMP280", description: "pressure sensor", addresses: &[(0x76, 0x77)] },
+    Chip { name: "BME280", description: "pressure/humidity sensor", addresses: &[(0x76, 0x77)] },
+];
+
+#[derive(Debug)]
+pub struct Found {
+    pub address: u16,
+    // Present or Busy
+    pub probe: Probe,
+    pub chips: Vec<&'static Chip>
+}
+
+pub fn identify(address: u16) -> Vec<&'static Chip> {
+    CHIPS.iter().filter(|chip| chip.responds_at(address)).collect()
+}
+
+// Regular 7-bit addresses, like i2cdetect without -a
+pub fn scan(bus: &mut I2CBus) -> Result<Vec<Found>> {
+    scan_range(bus, 0x03, 0x77)
+}
+
+pub fn scan_range(bus: &mut I2CBus, first: u16, last: u16) -> Result<Vec<Found>> {
+    let mut found = Vec::new();
+    for address in first..last + 1 {
+        match try!(bus.probe(address)) {
+            Probe::Empty => (),
+            probe => found.push(Found {
+                address: address,
+                probe: probe,
+                chips: identify(address)
+            })
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{MockI2CBus, MockI2CDevice, Probe};
+    use super::{scan, identify};
+
+    #[test]
+    fn scan_mock_bus() {
+        let unplugged = MockI2CDevice::new(0x48);
+        unplugged.set_connected(false);
+        let mut bus = MockI2CBus::new();
+        bus.add(MockI2CDevice::new(0x20))
+           .add(MockI2CDevice::new(0x76))
+           .add(unplugged)
+           .claim(0x68);
+
+        let found = scan(&mut bus).unwrap();
+        let addresses: Vec<(u16, Probe)> = found.iter().map(|f| (f.address, f.probe)).collect();
+        assert_eq!(addresses, vec![(0x20, Probe::Present), (0x68, Probe::Busy), (0x76, Probe::Present)]);
+
+        let names: Vec<&str> = found[2].chips.iter().map(|chip| chip.name).collect();
+        assert!(names.contains(&"BME280") && names.contains(&"TCA9548A"));
+        assert!(found[0].chips.iter().any(|chip| chip.name == "MCP23017"));
+    }
+
+    #[test]
+    fn identify_pca9685() {
+        let names = |address| identify(address).iter().map(|chip| chip.name).collect::<Vec<&str>>();
+        assert!(names(0x40).contains(&"PCA9685"));
+        assert!(names(0x70).contains(&"PCA9685"));
+        assert!(!names(0x48).contains(&"PCA9685"));
+        assert!(!names(0x68).contains(&"PCA9685"));
+    }
+}
@@ -6,6 +6,10 @@ extern crate spidev;
 extern crate core;
 extern crate nix;
 #[macro_use] extern crate bitflags;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "tokio")]
+extern crate futures_core;
 
 mod time;
 mod result;
@@ -13,11 +17,16 @@ mod map;
 mod logic;
 mod board;
 mod cupi;
+mod button;
+mod pulse;
 
 pub use time::{
     delay_usec,
     delay_ms,
-    delay_hard
+    delay_hard,
+    monotonic,
+    Clock,
+    MonotonicClock
 };
 
 pub use cupi::{
@@ -30,6 +39,8 @@ pub use logic::{
     DigitalLogic,
     DigitalWrite,
     DigitalRead,
+    IoPin,
+    OpenDrain,
     AnalogWrite,
     AnalogRead,
 };
@@ -39,6 +50,20 @@ pub use result::{
     Error
 };
 
+pub use button::{
+    Debounced,
+    Button,
+    ButtonEvent
+};
+
+pub use pulse::{
+    pulse_in,
+    pulse_in_with_clock,
+    pulse_in_events,
+    PulseIn,
+    FrequencyCounter
+};
+
 pub use board::{
     Board,
     Hardware,
@@ -54,11 +79,23 @@ pub mod bcm270x;
 pub use bcm270x::{
     PinOptions,
     PinInput,
-    PinOutput
+    PinOutput,
+    PinIO
 };
 
 pub mod sys;
-#[cfg(feature = "spi")]
+#[cfg(feature = "i2c")]
+pub mod i2c;
+pub mod spi;
+#[cfg(feature = "serial")]
+pub mod serial;
+#[cfg(feature = "serial")]
+pub mod modbus;
+#[cfg(feature = "can")]
+pub mod can;
+#[cfg(feature = "i2c")]
+pub mod pcf857x;
+pub mod onewire;
 pub mod mcp23x17;
 pub mod hat;
 
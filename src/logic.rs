@@ -112,6 +112,55 @@ pub trait DigitalWrite {
   }
 }
 
+// Pin that switches between driving and high impedance without being
+// dropped and reopened, for open drain, charlieplexing, 1-Wire, DHT sensors
+pub trait IoPin {
+  fn set_input(&mut self) -> Result<()>;
+
+  // The level is latched before the driver is enabled, no glitch
+  fn set_output<L: DigitalLogic>(&mut self, level: L) -> Result<()>;
+
+  // Level on the pin, also while driving
+  fn read(&mut self) -> Result<Logic>;
+
+  fn write3(&mut self, level: Logic3) -> Result<()> {
+      match level {
+          Logic3::High => self.set_output(Logic::High),
+          Logic3::Low => self.set_output(Logic::Low),
+          Logic3::Z => self.set_input()
+      }
+  }
+}
+
+// Open drain emulation, High releases the line to the pull-up
+pub struct OpenDrain<P: IoPin>(pub P);
+
+impl<P: IoPin> OpenDrain<P> {
+    pub fn new(mut pin: P) -> Result<OpenDrain<P>> {
+        try!(pin.set_input());
+        Ok(OpenDrain(pin))
+    }
+
+    pub fn into_inner(self) -> P {
+        self.0
+    }
+}
+
+impl<P: IoPin> DigitalWrite for OpenDrain<P> {
+  fn digital_write<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+      match level.logic_level() {
+          Logic::High => self.0.set_input(),
+          Logic::Low => self.0.set_output(Logic::Low)
+      }
+  }
+}
+
+impl<P: IoPin> DigitalRead for OpenDrain<P> {
+  fn digital_read(&mut self) -> Result<Logic> {
+      self.0.read()
+  }
+}
+
 pub trait AnalogRead {
   fn analog_read(&self) -> usize;
 }
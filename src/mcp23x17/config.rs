@@ -0,0 +1,54 @@
+use super::IOCONRegister;
+
+// IOCON settings for MCP23x17 chips. HAEN stays set so several
+// MCP23S17 can share one chip select.
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    iocon: IOCONRegister
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::new()
+    }
+}
+
+impl Config {
+    // BANK=0, byte mode, active low push-pull INT lines
+    pub fn new() -> Config {
+        Config { iocon: IOCONRegister::IOCON_SEQOP }
+    }
+
+    pub fn interrupt_active_high(&mut self, enable: bool) -> &mut Self {
+        self.iocon.set(IOCONRegister::IOCON_INTPOL, enable); self
+    }
+
+    // open-drain INT outputs, overrides the polarity
+    pub fn interrupt_open_drain(&mut self, enable: bool) -> &mut Self {
+        self.iocon.set(IOCONRegister::IOCON_ODR, enable); self
+    }
+
+    // INTA and INTB both signal interrupts of either port
+    pub fn interrupt_mirror(&mut self, enable: bool) -> &mut Self {
+        self.iocon.set(IOCONRegister::IOCON_MIRROR, enable); self
+    }
+
+    // SDA slew rate control (MCP23017 only)
+    pub fn slew_rate(&mut self, enable: bool) -> &mut Self {
+        self.iocon.set(IOCONRegister::IOCON_DISSLW, !enable); self
+    }
+
+    // registers of each port in separate banks
+    pub fn bank(&mut self, enable: bool) -> &mut Self {
+        self.iocon.set(IOCONRegister::IOCON_BANK_MODE, enable); self
+    }
+
+    // address pointer increments after each byte of a burst
+    pub fn sequential(&mut self, enable: bool) -> &mut Self {
+        self.iocon.set(IOCONRegister::IOCON_SEQOP, !enable); self
+    }
+
+    pub fn iocon(&self) -> IOCONRegister {
+        self.iocon | IOCONRegister::IOCON_HAEN
+    }
+}
@@ -0,0 +1,287 @@
+use std::io;
+use {Result, Error, RegisterDesc};
+use super::{MCP23X17Register, IOCONRegister, Transport};
+
+const REGISTERS: usize = 22;
+
+// Transport with a shadow copy of the configuration and output latch registers.
+// Cache order is also the restore order: latches before directions,
+// interrupts enabled last.
+const CACHED: [fn(usize) -> MCP23X17Register; 8] = [
+    MCP23X17Register::IOCON,
+    MCP23X17Register::OLAT,
+    MCP23X17Register::IODIR,
+    MCP23X17Register::IPOL,
+    MCP23X17Register::GPPU,
+    MCP23X17Register::DEFVAL,
+    MCP23X17Register::INTCON,
+    MCP23X17Register::GPINTEN
+];
+
+// Cached registers are written in one transaction without reading them back.
+// The cache is indexed by the BANK=0 offsets whatever map the chip uses.
+// MCP23x08 has one port laid out like port A in BANK=1 mode.
+pub(crate) struct Device {
+    transport: Box<Transport>,
+    cache: [u8; REGISTERS],
+    ports: usize,
+    bank: bool
+}
+
+fn is_cached(reg: &MCP23X17Register) -> bool {
+    match *reg {
+        MCP23X17Register::IODIR(_) |
+        MCP23X17Register::IPOL(_) |
+        MCP23X17Register::GPINTEN(_) |
+        MCP23X17Register::DEFVAL(_) |
+        MCP23X17Register::INTCON(_) |
+        MCP23X17Register::GPPU(_) |
+        MCP23X17Register::IOCON(_) |
+        MCP23X17Register::OLAT(_) => true,
+        _ => false
+    }
+}
+
+impl Device {
+    pub fn new(transport: Box<Transport>, ports: usize) -> Device {
+        Device { transport: transport, cache: [0; REGISTERS], ports: ports, bank: ports == 1 }
+    }
+
+    pub fn ports(&self) -> usize {
+        self.ports
+    }
+
+    // IOCON is shared by both ports, a new BANK map applies from the next access
+    fn configure(&mut self, mut iocon: IOCONRegister) -> io::Result<()> {
+        if self.ports == 1 {
+            // not implemented on MCP23x08
+            iocon.remove(IOCONRegister::IOCON_BANK_MODE | IOCONRegister::IOCON_MIRROR);
+        }
+        try!(self.write(MCP23X17Register::IOCON(0), iocon.bits()));
+        self.cache[MCP23X17Register::IOCON(1).offset()] = iocon.bits();
+        self.bank = self.ports == 1 || iocon.contains(IOCONRegister::IOCON_BANK_MODE);
+        Ok(())
+    }
+
+    // Reloads the cache from the chip
+    pub fn resync(&mut self) -> Result<()> {
+        for port in 0..self.ports {
+            for reg in CACHED.iter().map(|reg| reg(port)) {
+                self.cache[reg.offset()] = try!(self.transport.read(reg.address(self.bank)));
+            }
+        }
+        Ok(())
+    }
+
+    // Configures the chip and checks that IOCON reads back
+    pub fn attach(&mut self, iocon: IOCONRegister) -> Result<()> {
+        if self.configure(iocon).is_err() {
+            return Err(Error::DeviceNotResponding);
+        }
+        self.probe()
+    }
+
+    // Chip in its power-on state: HAEN goes to hardware address 0 first,
+    // then it answers at its own address
+    pub fn power_up(&mut self, iocon: IOCONRegister) -> Result<()> {
+        let address = MCP23X17Register::IOCON(0).address(self.bank);
+        if self.transport.write_unaddressed(address, IOCONRegister::IOCON_HAEN.bits()).is_err() {
+            return Err(Error::DeviceNotResponding);
+        }
+        self.attach(iocon)
+    }
+
+    fn probe(&mut self) -> Result<()> {
+        let iocon = MCP23X17Register::IOCON(0);
+        match self.transport.read(iocon.address(self.bank)) {
+            Ok(val) if val == self.cache[iocon.offset()] => Ok(()),
+            _ => Err(Error::DeviceNotResponding)
+        }
+    }
+
+    // A reset chip is back to IOCON=0 in its power-on map. HAEN is always set,
+    // so the cached IOCON never matches it. Returns true if the cached
+    // registers had to be written back.
+    pub fn health_check(&mut self) -> Result<bool> {
+        if self.probe().is_ok() {
+            return Ok(false);
+        }
+        // A failed probe alone may be a bus error. In BANK=1 the power-on IOCON
+        // address is another register, so ask the current map once more.
+        let power_on = self.ports == 1;
+        match self.transport.read(MCP23X17Register::IOCON(0).address(power_on)) {
+            Ok(0) => (),
+            _ => return Err(Error::DeviceNotResponding)
+        }
+        if self.probe().is_ok() {
+            return Ok(false);
+        }
+
+        let bank = self.bank;
+        self.bank = power_on;
+        match self.restore() {
+            Ok(()) => Ok(true),
+            Err(err) => {
+                self.bank = bank;
+                Err(err)
+            }
+        }
+    }
+
+    fn restore(&mut self) -> Result<()> {
+        let iocon = IOCONRegister::from_bits_truncate(self.cache[MCP23X17Register::IOCON(0).offset()]);
+        try!(self.power_up(iocon));
+        for reg in CACHED[1..].iter() {
+            for port in 0..self.ports {
+                let reg = reg(port);
+                try!(self.transport.write(reg.address(self.bank), self.cache[reg.offset()]));
+            }
+        }
+        Ok(())
+    }
+
+    // Port B does not exist on MCP23x08
+    fn check(&self, reg: MCP23X17Register) -> io::Result<()> {
+        if reg.offset() & 1 >= self.ports {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no such port"));
+        }
+        Ok(())
+    }
+
+    pub fn read(&mut self, reg: MCP23X17Register) -> io::Result<u8> {
+        try!(self.check(reg));
+        if is_cached(&reg) {
+            return Ok(self.cache[reg.offset()]);
+        }
+        self.transport.read(reg.address(self.bank))
+    }
+
+    pub fn write(&mut self, reg: MCP23X17Register, value: u8) -> io::Result<()> {
+        try!(self.check(reg));
+        try!(self.transport.write(reg.address(self.bank), value));
+        if is_cached(&reg) {
+            self.cache[reg.offset()] = value;
+        }
+        Ok(())
+    }
+
+    pub fn modify(&mut self, reg: MCP23X17Register, set: u8, clear: u8) -> io::Result<()> {
+        let val = try!(self.read(reg));
+        self.write(reg, (val | set) & !clear)
+    }
+
+    // Port A and B registers in one burst. In BANK=0 the pair is adjacent and
+    // the address pointer moves from A to B in both byte and sequential mode.
+    // In BANK=1 the ports are apart and take one transaction each.
+    pub fn read_pair(&mut self, reg: MCP23X17Register) -> io::Result<u16> {
+        let (a, b) = (reg.port(0), reg.port(1));
+        try!(self.check(b));
+        if is_cached(&reg) {
+            return Ok(self.cache[a.offset()] as u16 | (self.cache[b.offset()] as u16) << 8);
+        }
+        let mut buf = [0u8; 2];
+        if self.bank {
+            buf[0] = try!(self.read(a));
+            buf[1] = try!(self.read(b));
+        } else {
+            try!(self.transport.read_burst(a.address(false), &mut buf));
+        }
+        Ok(buf[0] as u16 | (buf[1] as u16) << 8)
+    }
+
+    pub fn write_pair(&mut self, reg: MCP23X17Register, value: u16) -> io::Result<()> {
+        let (a, b) = (reg.port(0), reg.port(1));
+        try!(self.check(b));
+        let buf = [value as u8, (value >> 8) as u8];
+        if self.bank {
+            try!(self.write(a, buf[0]));
+            return self.write(b, buf[1]);
+        }
+        try!(self.transport.write_burst(a.address(false), &buf));
+        if is_cached(&reg) {
+            self.cache[a.offset()] = buf[0];
+            self.cache[b.offset()] = buf[1];
+        }
+        Ok(())
+    }
+
+    pub fn modify_pair(&mut self, reg: MCP23X17Register, set: u16, clear: u16) -> io::Result<()> {
+        let val = try!(self.read_pair(reg));
+        self.write_pair(reg, (val | set) & !clear)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Error, DigitalWrite};
+    use sys::Edge;
+    use super::super::{MCP23X17, MCP23X17Register, Simulator, Config};
+
+    #[test]
+    fn probe_missing_chip() {
+        let sim = Simulator::new();
+        sim.set_connected(false);
+        match MCP23X17::mcp23x17(sim, &Config::new()) {
+            Err(Error::DeviceNotResponding) => (),
+            _ => panic!("chip should not respond")
+        }
+    }
+
+    #[test]
+    fn health_check_restores_after_reset() {
+        let sim = Simulator::new();
+        let chip = MCP23X17::mcp23x17(sim.clone(), &Config::new()).unwrap();
+        let mut pin = chip.porta().output(3).unwrap();
+        pin.high().unwrap();
+        let mut group = chip.portb().group_input(0x0F).unwrap();
+        group.interrupt(Edge::RisingEdge).unwrap();
+        assert_eq!(chip.health_check().unwrap(), false);
+
+        sim.reset();
+        assert_eq!(sim.outputs(0), 0);
+        assert_eq!(chip.health_check().unwrap(), true);
+        assert_eq!(sim.register(MCP23X17Register::IOCON(0)), 0x28);
+        assert_eq!(sim.outputs(0), 0x08);
+        assert_eq!(sim.register(MCP23X17Register::GPINTEN(1)), 0x0F);
+        assert_eq!(chip.health_check().unwrap(), false);
+
+        sim.set_connected(false);
+        match chip.health_check() {
+            Err(Error::DeviceNotResponding) => (),
+            _ => panic!("chip should not respond")
+        }
+    }
+
+    #[test]
+    fn health_check_bus_error_in_bank_mode() {
+        let sim = Simulator::new();
+        let mut config = Config::new();
+        config.bank(true);
+        let chip = MCP23X17::mcp23x17(sim.clone(), &config).unwrap();
+        let mut pin = chip.porta().output(3).unwrap();
+        pin.low().unwrap();
+
+        // no reset, only the probe fails. IOCON must not be written at its
+        // BANK=0 address, which is OLATA in the BANK=1 map.
+        sim.fail_next(1);
+        assert_eq!(chip.health_check().unwrap(), false);
+        assert_eq!(sim.outputs(0), 0);
+
+        // bus down for the whole check, the BANK=1 map is kept
+        sim.fail_next(10);
+        assert!(chip.health_check().is_err());
+        sim.fail_next(0);
+        assert_eq!(chip.health_check().unwrap(), false);
+        assert_eq!(sim.outputs(0), 0);
+        assert_eq!(sim.register(MCP23X17Register::IOCON(0)), 0xA8);
+
+        pin.high().unwrap();
+        assert_eq!(sim.outputs(0), 0x08);
+
+        // a real reset is still found and restored into BANK=1
+        sim.reset();
+        assert_eq!(chip.health_check().unwrap(), true);
+        assert_eq!(sim.register(MCP23X17Register::IOCON(0)), 0xA8);
+        assert_eq!(sim.outputs(0), 0x08);
+    }
+}
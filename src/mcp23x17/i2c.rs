@@ -0,0 +1,76 @@
+use std::io;
+use i2c::{I2CDevice, LinuxI2CDevice};
+use {Result, Error};
+use super::{MCP23X17, Transport, Config};
+
+pub struct I2CTransport {
+    dev: Box<I2CDevice>
+}
+
+impl I2CTransport {
+    pub fn new<D: I2CDevice + 'static>(dev: D) -> I2CTransport {
+        I2CTransport { dev: Box::new(dev) }
+    }
+
+    pub fn open(bus: usize, address: u16) -> Result<I2CTransport> {
+        Ok(I2CTransport::new(try!(LinuxI2CDevice::open(bus, address))))
+    }
+}
+
+impl Transport for I2CTransport {
+    fn read_burst(&mut self, reg: u8, buf: &mut [u8]) -> io::Result<()> {
+        self.dev.write_read(&[reg], buf)
+    }
+
+    fn write_burst(&mut self, reg: u8, data: &[u8]) -> io::Result<()> {
+        let mut tx = Vec::with_capacity(data.len() + 1);
+        tx.push(reg);
+        tx.extend_from_slice(data);
+        self.dev.write(&tx)
+    }
+}
+
+// address is the full 7-bit address 0x20-0x27
+fn open(bus: usize, address: u16) -> Result<I2CTransport> {
+    if address < 0x20 || address > 0x27 {
+        return Err(Error::InvalidAddress);
+    }
+    I2CTransport::open(bus, address)
+}
+
+// MCP23017, I2C variant of MCP23S17 on /dev/i2c-N
+pub type MCP23017 = MCP23X17<I2CTransport>;
+
+impl MCP23X17<I2CTransport> {
+    pub fn new(bus: usize, address: u16) -> Result<MCP23017> {
+        MCP23X17::mcp23x17(try!(open(bus, address)), &Config::new())
+    }
+
+    // 8-bit MCP23008, same registers as one MCP23017 port
+    pub fn mcp23008(bus: usize, address: u16) -> Result<MCP23017> {
+        MCP23X17::mcp23x08(try!(open(bus, address)), &Config::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use DigitalWrite;
+    use i2c::MockI2CDevice;
+    use super::super::{MCP23X17, Config};
+    use super::I2CTransport;
+
+    #[test]
+    fn mcp23017_on_mock_bus() {
+        let mock = MockI2CDevice::new(0x20);
+        mock.set_register(0x00, 0xFF);
+        mock.set_register(0x01, 0xFF);
+        // IOCON reads back what was written
+        let chip = MCP23X17::mcp23x17(I2CTransport::new(mock.clone()), &Config::new()).unwrap();
+        assert_eq!(mock.register(0x0A), 0x28);
+
+        let mut pin = chip.portb().output(1).unwrap();
+        pin.high().unwrap();
+        assert_eq!(mock.register(0x01), 0xFD);
+        assert_eq!(mock.register(0x15), 0x02);
+    }
+}
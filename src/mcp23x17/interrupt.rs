@@ -0,0 +1,205 @@
+use std::time::Duration;
+use mio::{Poll, Token};
+use sys::{self, Edge, EventDispatcher, EventId};
+#[cfg(feature = "tokio")]
+use std::collections::VecDeque;
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+#[cfg(feature = "tokio")]
+use std::task::{Context, Poll as TaskPoll};
+#[cfg(feature = "tokio")]
+use futures_core::Stream;
+use {Result, Logic, monotonic};
+use super::{MCP23X17Register, SharedBus, lock};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PinEvent {
+    pub port: usize,
+    pub pin: usize,
+    // level captured in INTCAP when the interrupt fired
+    pub level: Logic,
+    pub timestamp: Duration
+}
+
+// Decodes INTF/INTCAP of all ports into pin events.
+// Both ports of a x17 are always read, so it works with one INT line (MIRROR) or two.
+#[derive(Clone)]
+pub struct InterruptHandler {
+    bus: SharedBus
+}
+
+impl InterruptHandler {
+    pub(crate) fn new(bus: SharedBus) -> InterruptHandler {
+        InterruptHandler { bus: bus }
+    }
+
+    // Reads and clears pending interrupts
+    pub fn handle(&mut self) -> Result<Vec<PinEvent>> {
+        let timestamp = monotonic();
+        self.handle_at(timestamp)
+    }
+
+    fn handle_at(&mut self, timestamp: Duration) -> Result<Vec<PinEvent>> {
+        let mut bus = lock(&self.bus);
+        let mut events = Vec::new();
+
+        for port in 0..bus.ports() {
+            let intf = try!(bus.read(MCP23X17Register::INTF(port)));
+            // reading INTCAP releases the INT line
+            let intcap = try!(bus.read(MCP23X17Register::INTCAP(port)));
+            for pin in (0..8).filter(|pin| intf & (1 << pin) != 0) {
+                events.push(PinEvent {
+                    port: port,
+                    pin: pin,
+                    level: if intcap & (1 << pin) != 0 { Logic::High } else { Logic::Low },
+                    timestamp: timestamp
+                });
+            }
+        }
+        Ok(events)
+    }
+}
+
+// Interrupt handler attached to the sysfs pin wired to the chip INT output
+// (active low, open-drain or push-pull)
+pub struct Interrupts {
+    handler: InterruptHandler,
+    host: sys::PinInput
+}
+
+impl Interrupts {
+    pub fn new(handler: InterruptHandler, host: sys::PinInput) -> Interrupts {
+        Interrupts { handler: handler, host: host }
+    }
+
+    // Blocks until some expander pin fires, empty result means timeout
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Result<Vec<PinEvent>> {
+        try!(self.host.arm(Edge::FallingEdge));
+        // INT may already be asserted, then no falling edge will come
+        let pending = try!(self.handler.handle());
+        if !pending.is_empty() {
+            return Ok(pending);
+        }
+        match try!(self.host.next_event(timeout)) {
+            Some(event) => self.handler.handle_at(event.timestamp),
+            None => Ok(Vec::new())
+        }
+    }
+
+    pub fn trigger(&mut self, poll: &mut Poll, token: Token) -> Result<()> {
+        try!(self.host.trigger(poll, token, Edge::FallingEdge));
+        // release INT so the next interrupt makes an edge
+        let _ = try!(self.handler.handle());
+        Ok(())
+    }
+
+    pub fn stop_trigger(&mut self, poll: &mut Poll) -> Result<()> {
+        self.host.stop_trigger(poll)
+    }
+
+    // Call on mio readiness of the trigger token
+    pub fn handle(&mut self) -> Result<Vec<PinEvent>> {
+        let event = try!(self.host.event());
+        self.handler.handle_at(event.timestamp)
+    }
+
+    // Calls back for every expander pin event from the dispatcher thread
+    pub fn dispatch<F>(self, dispatcher: &mut EventDispatcher, mut callback: F) -> Result<EventId>
+        where F: FnMut(PinEvent) + Send + 'static
+    {
+        let mut handler = self.handler;
+        let _ = try!(handler.handle());
+        dispatcher.add(self.host, Edge::FallingEdge, None, move |event| {
+            if let Ok(events) = handler.handle_at(event.timestamp) {
+                for e in events {
+                    callback(e);
+                }
+            }
+        })
+    }
+
+    #[cfg(feature = "tokio")]
+    pub fn events(&mut self) -> Result<PinEvents> {
+        let events = try!(self.host.events(Edge::FallingEdge));
+        let pending: VecDeque<PinEvent> = try!(self.handler.handle()).into_iter().collect();
+        Ok(PinEvents { handler: &mut self.handler, events: events, pending: pending })
+    }
+
+    pub fn into_inner(self) -> (InterruptHandler, sys::PinInput) {
+        (self.handler, self.host)
+    }
+}
+
+#[cfg(feature = "tokio")]
+pub struct PinEvents<'a> {
+    handler: &'a mut InterruptHandler,
+    events: sys::PinEvents<'a>,
+    pending: VecDeque<PinEvent>
+}
+
+#[cfg(feature = "tokio")]
+impl<'a> Stream for PinEvents<'a> {
+    type Item = Result<PinEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> TaskPoll<Option<Result<PinEvent>>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(event) = this.pending.pop_front() {
+                return TaskPoll::Ready(Some(Ok(event)));
+            }
+            match Pin::new(&mut this.events).poll_next(cx) {
+                TaskPoll::Ready(Some(Ok(event))) => match this.handler.handle_at(event.timestamp) {
+                    Ok(events) => this.pending.extend(events),
+                    Err(err) => return TaskPoll::Ready(Some(Err(err)))
+                },
+                TaskPoll::Ready(Some(Err(err))) => return TaskPoll::Ready(Some(Err(err))),
+                TaskPoll::Ready(None) => return TaskPoll::Ready(None),
+                TaskPoll::Pending => return TaskPoll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use Logic;
+    use sys::Edge;
+    use super::super::{MCP23X17, Simulator, Config};
+
+    #[test]
+    fn decode_both_ports() {
+        let sim = Simulator::new();
+        let chip = MCP23X17::mcp23x17(sim.clone(), &Config::new()).unwrap();
+        let mut a = chip.porta().group_input(0xFF).unwrap();
+        let mut b = chip.portb().group_input(0xFF).unwrap();
+        a.interrupt(Edge::BothEdges).unwrap();
+        b.interrupt(Edge::FallingEdge).unwrap();
+        sim.set_inputs(1, 0xFF);
+        let mut handler = chip.interrupt_handler();
+        handler.handle().unwrap();
+
+        sim.set_inputs(0, 0x04);
+        sim.set_inputs(1, 0x7F);
+        assert!(sim.interrupt(0) && sim.interrupt(1));
+
+        let events: Vec<(usize, usize, Logic)> = handler.handle().unwrap()
+            .into_iter().map(|e| (e.port, e.pin, e.level)).collect();
+        assert_eq!(events, vec![(0, 2, Logic::High), (1, 7, Logic::Low)]);
+        assert!(!sim.interrupt(0) && !sim.interrupt(1));
+        assert!(handler.handle().unwrap().is_empty());
+    }
+
+    #[test]
+    fn rising_edge_ignores_falling() {
+        let sim = Simulator::new();
+        let chip = MCP23X17::mcp23x17(sim.clone(), &Config::new()).unwrap();
+        let mut a = chip.porta().group_input(0x01).unwrap();
+        a.interrupt(Edge::RisingEdge).unwrap();
+        let mut handler = chip.interrupt_handler();
+
+        sim.set_inputs(0, 0x01);
+        assert_eq!(handler.handle().unwrap().len(), 1);
+        sim.set_inputs(0, 0x00);
+        assert!(handler.handle().unwrap().is_empty());
+    }
+}
@@ -1,9 +1,54 @@
-use spidev::{Spidev, SpidevOptions, SpidevTransfer, SPI_MODE_0};
-use {Result, RegisterDesc, Logic, DigitalLogic, DigitalWrite, DigitalRead};
 use std::io;
-use std::sync::{Arc, Mutex};
-use sys::Edge;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, MutexGuard};
+use {Result, RegisterDesc};
 
+mod port;
+mod config;
+mod device;
+mod interrupt;
+#[cfg(feature = "spi")]
+mod spi;
+#[cfg(feature = "i2c")]
+mod i2c;
+mod simulator;
+
+pub use self::port::{
+    Port,
+    PinInput,
+    PinOutput,
+    PinIO,
+    GroupInput,
+    GroupOutput,
+    Bus16
+};
+
+#[cfg(feature = "tokio")]
+pub use self::port::InterruptEvents;
+
+pub use self::config::Config;
+
+pub use self::interrupt::{
+    PinEvent,
+    InterruptHandler,
+    Interrupts
+};
+
+#[cfg(feature = "tokio")]
+pub use self::interrupt::PinEvents;
+
+#[cfg(feature = "spi")]
+pub use self::spi::{MCP23S17, MCP23S17Builder, MCP23S17Bus, SpiTransport};
+
+#[cfg(feature = "i2c")]
+pub use self::i2c::{MCP23017, I2CTransport};
+
+pub use self::simulator::Simulator;
+
+use self::device::Device;
+
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum MCP23X17Register {
     IODIR(usize),
     IPOL(usize),
@@ -37,35 +82,36 @@ impl RegisterDesc for MCP23X17Register {
 }
 
 impl MCP23X17Register {
-    pub fn write(&self, spi: &Spidev, address: usize, value: u8) -> io::Result<()> {
-        let mut tr = SpidevTransfer::write(&[
-            (CMD_WRITE | (address << 1)) as u8,
-            self.offset() as u8,
-            value
-        ]);
-        try!(spi.transfer(&mut tr));
-        Ok(())
+    // Same register of the other port
+    pub fn port(&self, port: usize) -> MCP23X17Register {
+        match *self {
+            MCP23X17Register::IODIR(_)   => MCP23X17Register::IODIR(port),
+            MCP23X17Register::IPOL(_)    => MCP23X17Register::IPOL(port),
+            MCP23X17Register::GPINTEN(_) => MCP23X17Register::GPINTEN(port),
+            MCP23X17Register::DEFVAL(_)  => MCP23X17Register::DEFVAL(port),
+            MCP23X17Register::INTCON(_)  => MCP23X17Register::INTCON(port),
+            MCP23X17Register::IOCON(_)   => MCP23X17Register::IOCON(port),
+            MCP23X17Register::GPPU(_)    => MCP23X17Register::GPPU(port),
+            MCP23X17Register::INTF(_)    => MCP23X17Register::INTF(port),
+            MCP23X17Register::INTCAP(_)  => MCP23X17Register::INTCAP(port),
+            MCP23X17Register::GPIO(_)    => MCP23X17Register::GPIO(port),
+            MCP23X17Register::OLAT(_)    => MCP23X17Register::OLAT(port),
+        }
     }
 
-    pub fn read(&self, spi: &Spidev, address: usize ) -> io::Result<u8> {
-        let mut tr = SpidevTransfer::write(&[
-            (CMD_READ | (address << 1)) as u8,
-            self.offset() as u8,
-            0
-        ]);
-        try!(spi.transfer(&mut tr));
-        match tr.rx_buf {
-            Some(ref rx_buf) => Ok(rx_buf[2]),
-            _ => panic!()
+    // Address in the BANK=1 map when bank is set, offset() is the BANK=0 map
+    pub fn address(&self, bank: bool) -> u8 {
+        let offset = self.offset();
+        if bank {
+            ((offset & 1) << 4 | offset >> 1) as u8
+        } else {
+            offset as u8
         }
     }
 }
 
-const CMD_WRITE: usize = 0x40;
-const CMD_READ: usize  = 0x41;
-
 bitflags! {
-    struct IOCONRegister: u8 {
+    pub struct IOCONRegister: u8 {
         const IOCON_UNUSED = 0x01;
 	    const IOCON_INTPOL = 0x02;
 	    const IOCON_ODR	   = 0x04;
@@ -77,361 +123,94 @@ bitflags! {
     }
 }
 
-pub struct MCP23S17 {
-    spi: Arc<Mutex<Spidev>>,
-    address: usize
-}
+// Register access of one chip, SPI and I2C differ only here.
+// Bursts follow the chip address pointer (see IOCON SEQOP).
+pub trait Transport: Send {
+    fn read_burst(&mut self, reg: u8, buf: &mut [u8]) -> io::Result<()>;
+    fn write_burst(&mut self, reg: u8, data: &[u8]) -> io::Result<()>;
 
-impl MCP23S17 {
-    pub unsafe fn new(address: usize) -> Result<Self> {
-        let mut spi = try!(Spidev::open("/dev/spidev0.0"));
-        assert!(address <= 8);
-        let mut options = SpidevOptions::new();
-        options.bits_per_word(8)
-               .max_speed_hz(4_000_000)
-               .mode(SPI_MODE_0);
-
-        try!(spi.configure(&options));
-
-        let iocona = MCP23X17Register::IOCON(0);
-        try!(iocona.write(&spi, address, (IOCONRegister::IOCON_SEQOP | IOCONRegister::IOCON_HAEN).bits()));
-        let ioconb = MCP23X17Register::IOCON(1);
-        try!(ioconb.write(&spi, address, (IOCONRegister::IOCON_SEQOP | IOCONRegister::IOCON_HAEN).bits()));
-
-        Ok(MCP23S17 {
-            spi: Arc::new(Mutex::new(spi)),
-            address: address
-        })
+    fn read(&mut self, reg: u8) -> io::Result<u8> {
+        let mut buf = [0u8];
+        try!(self.read_burst(reg, &mut buf));
+        Ok(buf[0])
     }
 
-    pub fn porta(&self) -> Port {
-        Port { spi: self.spi.clone(), address: self.address, port: 0 }
+    fn write(&mut self, reg: u8, value: u8) -> io::Result<()> {
+        self.write_burst(reg, &[value])
     }
 
-    pub fn portb(&self) -> Port {
-        Port { spi: self.spi.clone(), address: self.address, port: 1 }
+    // Write reaching a chip whose HAEN is still clear, on SPI that is
+    // hardware address 0 whatever the chip is strapped to
+    fn write_unaddressed(&mut self, reg: u8, value: u8) -> io::Result<()> {
+        self.write(reg, value)
     }
 }
 
-pub struct Port {
-    spi: Arc<Mutex<Spidev>>,
-    address: usize,
-    port: usize,
-}
-
-impl Port {
-    pub fn input(&mut self, pin: usize) -> Result<PinInput> {
-        assert!(pin < 8);
-        let spi = match self.spi.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-
-        let mask = 1 << pin;
-        let iodir = MCP23X17Register::IODIR(self.port);
-        // modify bit
-        let mut dir = try!(iodir.read(&spi, self.address));
-        dir |= mask;
-        try!(iodir.write(&spi, self.address, dir));
-
-        Ok(PinInput {
-            spi: self.spi.clone(),
-            address: self.address,
-            port: self.port,
-            pin: pin
-        })
-    }
+pub(crate) type SharedBus = Arc<Mutex<Device>>;
 
-    pub fn output(&mut self, pin: usize) -> Result<PinOutput> {
-        assert!(pin < 8);
-        let spi = match self.spi.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-
-        let mask = 1 << pin;
-        let iodir = MCP23X17Register::IODIR(self.port);
-        // modify bit
-        let mut dir = try!(iodir.read(&spi, self.address));
-        dir &= !mask;
-        try!(iodir.write(&spi, self.address, dir));
-
-        Ok(PinOutput {
-            spi: self.spi.clone(),
-            address: self.address,
-            port: self.port,
-            pin: pin
-        })
+pub(crate) fn lock(bus: &SharedBus) -> MutexGuard<Device> {
+    match bus.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
     }
-
-    pub fn group_output(&mut self, mask: u8) -> Result<GroupOutput> {
-        let spi = match self.spi.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-
-        let iodir = MCP23X17Register::IODIR(self.port);
-        let mut dir = try!(iodir.read(&spi, self.address));
-        dir &= !mask;
-        try!(iodir.write(&spi, self.address, dir));
-
-        Ok(GroupOutput {
-            spi: self.spi.clone(),
-            address: self.address,
-            port: self.port,
-            mask: mask as u8
-        })
-    }
-
-    pub fn group_input(&mut self, mask: u8) -> Result<GroupInput> {
-        let spi = match self.spi.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-
-        let iodir = MCP23X17Register::IODIR(self.port);
-        // modify bit
-        let mut dir = try!(iodir.read(&spi, self.address));
-        dir |= mask;
-        try!(iodir.write(&spi, self.address, dir));
-
-        Ok(GroupInput {
-            spi: self.spi.clone(),
-            address: self.address,
-            port: self.port,
-            mask: mask as u8
-        })
-    }
-}
-
-pub struct PinInput {
-    spi: Arc<Mutex<Spidev>>,
-    address: usize,
-    port: usize,
-    pin: usize
 }
 
-impl PinInput {
-    pub fn pull_up(&mut self) -> Result<()> {
-        let spi = match self.spi.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-
-        let mask = 1 << self.pin;
-        let iodir = MCP23X17Register::GPPU(self.port);
-        // modify bit
-        let mut dir = try!(iodir.read(&spi, self.address));
-        dir |= mask;
-        try!(iodir.write(&spi, self.address, dir));
-        Ok(())
-    }
-
-    pub fn pull_off(&mut self) -> Result<()> {
-        let spi = match self.spi.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-
-        let mask = 1 << self.pin;
-        let iodir = MCP23X17Register::GPPU(self.port);
-        // modify bit
-        let mut dir = try!(iodir.read(&spi, self.address));
-        dir &= !mask;
-        try!(iodir.write(&spi, self.address, dir));
-        Ok(())
-    }
+// Chip on any transport, e.g. Simulator in tests. The same type drives the
+// 16-pin MCP23x17 and the 8-pin MCP23x08, which has only port A: port B and
+// bus16 accesses fail on it with InvalidInput.
+pub struct MCP23X17<T> {
+    bus: SharedBus,
+    // the transport itself is boxed in the Device
+    transport: PhantomData<fn() -> T>
 }
 
-impl DigitalRead for PinInput {
-    fn digital_read(&mut self) -> Result<Logic> {
-        let spi = match self.spi.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-
-        let mask = 1 << self.pin;
-        let gpio = MCP23X17Register::GPIO(self.port);
-        let val = try!(gpio.read(&spi, self.address));
-
-        match val & mask {
-            0 => Ok(Logic::Low),
-            _ => Ok(Logic::High)
-        }
+impl<T: Transport + 'static> MCP23X17<T> {
+    pub fn mcp23x17(transport: T, config: &Config) -> Result<MCP23X17<T>> {
+        MCP23X17::init(transport, 2, config)
     }
-}
 
-pub struct PinOutput {
-    spi: Arc<Mutex<Spidev>>,
-    address: usize,
-    port: usize,
-    pin: usize
-}
-
-impl DigitalWrite for PinOutput {
-    fn digital_write<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
-      let spi = match self.spi.lock() {
-          Ok(guard) => guard,
-          Err(poisoned) => poisoned.into_inner(),
-      };
-      let bit: u8 = 1 << self.pin;
-      let gpio = MCP23X17Register::GPIO(self.port);
-      let mut val = try!(gpio.read(&spi, self.address));
-      match level.logic_level() {
-          Logic::Low  => val &= !bit,
-          Logic::High => val |= bit
-      }
-      try!(gpio.write(&spi, self.address, val));
-      Ok(())
+    // BANK and MIRROR are ignored
+    pub fn mcp23x08(transport: T, config: &Config) -> Result<MCP23X17<T>> {
+        MCP23X17::init(transport, 1, config)
     }
-}
 
-impl Drop for PinOutput {
-    fn drop(&mut self) {
-        let spi = match self.spi.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-        let mask = 1 << self.pin;
-        let iodir = MCP23X17Register::IODIR(self.port);
-        // make pin input
-        if let Ok(mut dir) = iodir.read(&spi, self.address) {
-            dir &= !mask;
-            let _ = iodir.write(&spi, self.address, dir);
-        }
+    fn init(transport: T, ports: usize, config: &Config) -> Result<MCP23X17<T>> {
+        // expects the power-on register map
+        let mut dev = Device::new(Box::new(transport), ports);
+        try!(dev.power_up(config.iocon()));
+        try!(dev.resync());
+        Ok(MCP23X17 { bus: Arc::new(Mutex::new(dev)), transport: PhantomData })
     }
 }
 
-pub struct GroupInput {
-    spi: Arc<Mutex<Spidev>>,
-    address: usize,
-    port: usize,
-    mask: u8
-}
-
-impl GroupInput {
-    pub fn pull_up(&mut self) -> Result<()> {
-        let spi = match self.spi.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-
-        let iodir = MCP23X17Register::GPPU(self.port);
-        let mut dir = try!(iodir.read(&spi, self.address));
-        dir |= self.mask;
-        try!(iodir.write(&spi, self.address, dir));
-        Ok(())
+impl<T> MCP23X17<T> {
+    pub fn porta(&self) -> Port {
+        Port::new(self.bus.clone(), 0)
     }
 
-    pub fn pull_off(&mut self) -> Result<()> {
-        let spi = match self.spi.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-
-        let iodir = MCP23X17Register::GPPU(self.port);
-        let mut dir = try!(iodir.read(&spi, self.address));
-        dir &= !self.mask;
-        try!(iodir.write(&spi, self.address, dir));
-        Ok(())
+    pub fn portb(&self) -> Port {
+        Port::new(self.bus.clone(), 1)
     }
 
-    pub fn digital_read(&mut self) -> Result<u8> {
-        let spi = match self.spi.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-
-        let gpio = MCP23X17Register::GPIO(self.port);
-        let val = try!(gpio.read(&spi, self.address));
-
-        Ok(val & self.mask)
+    pub fn bus16(&self, mask: u16) -> Bus16 {
+        Bus16::new(self.bus.clone(), mask)
     }
 
-    pub fn interrupt(&mut self, edge: Edge) -> Result<()> {
-        let spi = match self.spi.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-
-        let defval = MCP23X17Register::DEFVAL(self.port);
-        let mut val = try!(defval.read(&spi, self.address));
-        match edge {
-            Edge::RisingEdge  => val |= self.mask,
-            Edge::FallingEdge => val &= !self.mask,
-            Edge::BothEdges   => (),
-            Edge::NoInterrupt => ()
-        }
-        println!("{:b}", val);
-        try!(defval.write(&spi, self.address, val));
-
-        let intcon = MCP23X17Register::INTCON(self.port);
-        let mut val = try!(intcon.read(&spi, self.address));
-        match edge {
-            Edge::RisingEdge  => val |= self.mask,
-            Edge::FallingEdge => val |= self.mask,
-            Edge::BothEdges   => val &= !self.mask,
-            Edge::NoInterrupt => ()
-        }
-        println!("{:b}", val);
-        try!(intcon.write(&spi, self.address, val));
-
-        // enable interrupts on masked pins
-        let gpinten = MCP23X17Register::GPINTEN(self.port);
-        let mut val = try!(gpinten.read(&spi, self.address));
-
-        match edge {
-            Edge::RisingEdge  => val |= self.mask,
-            Edge::FallingEdge => val |= self.mask,
-            Edge::BothEdges   => val |= self.mask,
-            Edge::NoInterrupt => val &= !self.mask
-        }
-        println!("{:b}", val);
-        try!(gpinten.write(&spi, self.address, val));
-        Ok(())
+    // Changes IOCON at runtime, BANK switches the register map for later accesses
+    pub fn configure(&self, config: &Config) -> Result<()> {
+        lock(&self.bus).attach(config.iocon())
     }
 
-    pub fn stop_interrupt(&mut self) -> Result<()> {
-        Ok(try!(self.interrupt(Edge::NoInterrupt)))
+    // Call periodically, true when the chip was found reset and reconfigured
+    pub fn health_check(&self) -> Result<bool> {
+        lock(&self.bus).health_check()
     }
-}
-
-
-pub struct GroupOutput {
-    spi: Arc<Mutex<Spidev>>,
-    address: usize,
-    port: usize,
-    mask: u8
-}
 
-impl DigitalWrite for GroupOutput {
-    fn digital_write<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
-      let spi = match self.spi.lock() {
-          Ok(guard) => guard,
-          Err(poisoned) => poisoned.into_inner(),
-      };
-      let gpio = MCP23X17Register::GPIO(self.port);
-      let mut val = try!(gpio.read(&spi, self.address));
-      match level.logic_level() {
-          Logic::Low  => val &= !self.mask,
-          Logic::High => val |= self.mask
-      }
-      try!(gpio.write(&spi, self.address, val));
-      Ok(())
+    pub fn resync(&self) -> Result<()> {
+        lock(&self.bus).resync()
     }
-}
 
-impl Drop for GroupOutput {
-    fn drop(&mut self) {
-        let spi = match self.spi.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-        let iodir = MCP23X17Register::IODIR(self.port);
-        // make pin input
-        if let Ok(mut dir) = iodir.read(&spi, self.address) {
-            dir &= !self.mask;
-            let _ = iodir.write(&spi, self.address, dir);
-        }
+    pub fn interrupt_handler(&self) -> InterruptHandler {
+        InterruptHandler::new(self.bus.clone())
     }
 }
@@ -0,0 +1,670 @@
+use {Result, Error, Logic, DigitalLogic, DigitalWrite, DigitalRead, IoPin};
+use sys::Edge;
+#[cfg(feature = "tokio")]
+use sys;
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+#[cfg(feature = "tokio")]
+use std::task::{Context, Poll};
+#[cfg(feature = "tokio")]
+use futures_core::Stream;
+use super::{MCP23X17Register, SharedBus, lock};
+
+pub struct Port {
+    bus: SharedBus,
+    port: usize,
+}
+
+impl Port {
+    pub(crate) fn new(bus: SharedBus, port: usize) -> Port {
+        Port { bus: bus, port: port }
+    }
+
+    pub fn input(&mut self, pin: usize) -> Result<PinInput> {
+        if pin >= 8 {
+            return Err(Error::InvalidAddress);
+        }
+        let mut bus = lock(&self.bus);
+
+        let mask = 1 << pin;
+        // modify bit
+        try!(bus.modify(MCP23X17Register::IODIR(self.port), mask, 0));
+
+        Ok(PinInput {
+            bus: self.bus.clone(),
+            port: self.port,
+            pin: pin
+        })
+    }
+
+    pub fn output(&mut self, pin: usize) -> Result<PinOutput> {
+        if pin >= 8 {
+            return Err(Error::InvalidAddress);
+        }
+        let mut bus = lock(&self.bus);
+
+        let mask = 1 << pin;
+        // modify bit
+        try!(bus.modify(MCP23X17Register::IODIR(self.port), 0, mask));
+
+        Ok(PinOutput {
+            bus: self.bus.clone(),
+            port: self.port,
+            pin: pin
+        })
+    }
+
+    // Starts as input
+    pub fn io(&mut self, pin: usize) -> Result<PinIO> {
+        if pin >= 8 {
+            return Err(Error::InvalidAddress);
+        }
+        let mut bus = lock(&self.bus);
+        try!(bus.modify(MCP23X17Register::IODIR(self.port), 1 << pin, 0));
+
+        Ok(PinIO {
+            bus: self.bus.clone(),
+            port: self.port,
+            pin: pin
+        })
+    }
+
+    pub fn group_output(&mut self, mask: u8) -> Result<GroupOutput> {
+        let mut bus = lock(&self.bus);
+        try!(bus.modify(MCP23X17Register::IODIR(self.port), 0, mask));
+
+        Ok(GroupOutput {
+            bus: self.bus.clone(),
+            port: self.port,
+            mask: mask
+        })
+    }
+
+    pub fn group_input(&mut self, mask: u8) -> Result<GroupInput> {
+        let mut bus = lock(&self.bus);
+        try!(bus.modify(MCP23X17Register::IODIR(self.port), mask, 0));
+
+        Ok(GroupInput {
+            bus: self.bus.clone(),
+            port: self.port,
+            mask: mask
+        })
+    }
+}
+
+pub struct PinInput {
+    bus: SharedBus,
+    port: usize,
+    pin: usize
+}
+
+impl PinInput {
+    pub fn pull_up(&mut self) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        let mask = 1 << self.pin;
+        try!(bus.modify(MCP23X17Register::GPPU(self.port), mask, 0));
+        Ok(())
+    }
+
+    pub fn pull_off(&mut self) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        let mask = 1 << self.pin;
+        try!(bus.modify(MCP23X17Register::GPPU(self.port), 0, mask));
+        Ok(())
+    }
+
+    // reads (and interrupt captures) the opposite of the pin level
+    pub fn invert(&mut self, invert: bool) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        let mask = 1 << self.pin;
+        let ipol = MCP23X17Register::IPOL(self.port);
+        match invert {
+            true  => try!(bus.modify(ipol, mask, 0)),
+            false => try!(bus.modify(ipol, 0, mask))
+        }
+        Ok(())
+    }
+}
+
+impl DigitalRead for PinInput {
+    fn digital_read(&mut self) -> Result<Logic> {
+        let mut bus = lock(&self.bus);
+
+        let mask = 1 << self.pin;
+        let val = try!(bus.read(MCP23X17Register::GPIO(self.port)));
+
+        match val & mask {
+            0 => Ok(Logic::Low),
+            _ => Ok(Logic::High)
+        }
+    }
+}
+
+pub struct PinOutput {
+    bus: SharedBus,
+    port: usize,
+    pin: usize
+}
+
+impl DigitalWrite for PinOutput {
+    fn digital_write<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+      let mut bus = lock(&self.bus);
+      let bit: u8 = 1 << self.pin;
+      let olat = MCP23X17Register::OLAT(self.port);
+      match level.logic_level() {
+          Logic::Low  => try!(bus.modify(olat, 0, bit)),
+          Logic::High => try!(bus.modify(olat, bit, 0))
+      }
+      Ok(())
+    }
+}
+
+impl Drop for PinOutput {
+    fn drop(&mut self) {
+        let mut bus = lock(&self.bus);
+        let mask = 1 << self.pin;
+        // make pin input
+        let _ = bus.modify(MCP23X17Register::IODIR(self.port), mask, 0);
+    }
+}
+
+pub struct PinIO {
+    bus: SharedBus,
+    port: usize,
+    pin: usize
+}
+
+impl IoPin for PinIO {
+    fn set_input(&mut self) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        try!(bus.modify(MCP23X17Register::IODIR(self.port), 1 << self.pin, 0));
+        Ok(())
+    }
+
+    // latch first, so the pin never drives the previous level
+    fn set_output<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        let bit: u8 = 1 << self.pin;
+        let olat = MCP23X17Register::OLAT(self.port);
+        match level.logic_level() {
+            Logic::Low  => try!(bus.modify(olat, 0, bit)),
+            Logic::High => try!(bus.modify(olat, bit, 0))
+        }
+        try!(bus.modify(MCP23X17Register::IODIR(self.port), 0, bit));
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Logic> {
+        let mut bus = lock(&self.bus);
+        let val = try!(bus.read(MCP23X17Register::GPIO(self.port)));
+        match val & (1 << self.pin) {
+            0 => Ok(Logic::Low),
+            _ => Ok(Logic::High)
+        }
+    }
+}
+
+impl Drop for PinIO {
+    fn drop(&mut self) {
+        let mut bus = lock(&self.bus);
+        let _ = bus.modify(MCP23X17Register::IODIR(self.port), 1 << self.pin, 0);
+    }
+}
+
+pub struct GroupInput {
+    bus: SharedBus,
+    port: usize,
+    mask: u8
+}
+
+impl GroupInput {
+    pub fn pull_up(&mut self) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        try!(bus.modify(MCP23X17Register::GPPU(self.port), self.mask, 0));
+        Ok(())
+    }
+
+    pub fn pull_off(&mut self) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        try!(bus.modify(MCP23X17Register::GPPU(self.port), 0, self.mask));
+        Ok(())
+    }
+
+    pub fn invert(&mut self, invert: bool) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        let ipol = MCP23X17Register::IPOL(self.port);
+        match invert {
+            true  => try!(bus.modify(ipol, self.mask, 0)),
+            false => try!(bus.modify(ipol, 0, self.mask))
+        }
+        Ok(())
+    }
+
+    pub fn digital_read(&mut self) -> Result<u8> {
+        let mut bus = lock(&self.bus);
+        let val = try!(bus.read(MCP23X17Register::GPIO(self.port)));
+        Ok(val & self.mask)
+    }
+
+    pub fn interrupt(&mut self, edge: Edge) -> Result<()> {
+        let mut bus = lock(&self.bus);
+
+        // compare mode fires while the pin differs from DEFVAL
+        let defval = MCP23X17Register::DEFVAL(self.port);
+        match edge {
+            Edge::RisingEdge  => try!(bus.modify(defval, 0, self.mask)),
+            Edge::FallingEdge => try!(bus.modify(defval, self.mask, 0)),
+            Edge::BothEdges   => (),
+            Edge::NoInterrupt => ()
+        }
+
+        let intcon = MCP23X17Register::INTCON(self.port);
+        match edge {
+            Edge::RisingEdge  => try!(bus.modify(intcon, self.mask, 0)),
+            Edge::FallingEdge => try!(bus.modify(intcon, self.mask, 0)),
+            Edge::BothEdges   => try!(bus.modify(intcon, 0, self.mask)),
+            Edge::NoInterrupt => ()
+        }
+
+        // enable interrupts on masked pins
+        let gpinten = MCP23X17Register::GPINTEN(self.port);
+        match edge {
+            Edge::NoInterrupt => try!(bus.modify(gpinten, 0, self.mask)),
+            _ => try!(bus.modify(gpinten, self.mask, 0))
+        }
+        Ok(())
+    }
+
+    pub fn stop_interrupt(&mut self) -> Result<()> {
+        Ok(try!(self.interrupt(Edge::NoInterrupt)))
+    }
+
+    // Stream of port values, one per falling edge on the chip INT line.
+    // Reading GPIO after each edge releases the INT line.
+    #[cfg(feature = "tokio")]
+    pub fn interrupt_events<'a>(&'a mut self, host: &'a mut sys::PinInput) -> Result<InterruptEvents<'a>> {
+        let events = try!(host.events(Edge::FallingEdge));
+        // release INT if it was already asserted, otherwise no edge will come
+        let _ = try!(self.digital_read());
+        Ok(InterruptEvents { group: self, events: events })
+    }
+}
+
+#[cfg(feature = "tokio")]
+pub struct InterruptEvents<'a> {
+    group: &'a mut GroupInput,
+    events: sys::PinEvents<'a>
+}
+
+#[cfg(feature = "tokio")]
+impl<'a> Stream for InterruptEvents<'a> {
+    type Item = Result<u8>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<u8>>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.events).poll_next(cx) {
+            Poll::Ready(Some(Ok(_))) => Poll::Ready(Some(this.group.digital_read())),
+            Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending
+        }
+    }
+}
+
+pub struct GroupOutput {
+    bus: SharedBus,
+    port: usize,
+    mask: u8
+}
+
+impl GroupOutput {
+    // Sets masked bits to value, other pins keep their latch
+    pub fn write_bits(&mut self, value: u8) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        let olat = MCP23X17Register::OLAT(self.port);
+        try!(bus.modify(olat, value & self.mask, !value & self.mask));
+        Ok(())
+    }
+}
+
+impl DigitalWrite for GroupOutput {
+    fn digital_write<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+      let mut bus = lock(&self.bus);
+      let olat = MCP23X17Register::OLAT(self.port);
+      match level.logic_level() {
+          Logic::Low  => try!(bus.modify(olat, 0, self.mask)),
+          Logic::High => try!(bus.modify(olat, self.mask, 0))
+      }
+      Ok(())
+    }
+}
+
+impl Drop for GroupOutput {
+    fn drop(&mut self) {
+        let mut bus = lock(&self.bus);
+        // make pin input
+        let _ = bus.modify(MCP23X17Register::IODIR(self.port), self.mask, 0);
+    }
+}
+
+// Port A (low byte) and port B (high byte) as one 16-bit bus,
+// every access is a single SPI/I2C burst unless in BANK mode
+pub struct Bus16 {
+    bus: SharedBus,
+    mask: u16
+}
+
+impl Bus16 {
+    pub(crate) fn new(bus: SharedBus, mask: u16) -> Bus16 {
+        Bus16 { bus: bus, mask: mask }
+    }
+
+    pub fn set_output(&mut self) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        try!(bus.modify_pair(MCP23X17Register::IODIR(0), 0, self.mask));
+        Ok(())
+    }
+
+    pub fn set_input(&mut self) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        try!(bus.modify_pair(MCP23X17Register::IODIR(0), self.mask, 0));
+        Ok(())
+    }
+
+    pub fn pull_up(&mut self) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        try!(bus.modify_pair(MCP23X17Register::GPPU(0), self.mask, 0));
+        Ok(())
+    }
+
+    pub fn pull_off(&mut self) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        try!(bus.modify_pair(MCP23X17Register::GPPU(0), 0, self.mask));
+        Ok(())
+    }
+
+    pub fn invert(&mut self, invert: bool) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        let ipol = MCP23X17Register::IPOL(0);
+        match invert {
+            true  => try!(bus.modify_pair(ipol, self.mask, 0)),
+            false => try!(bus.modify_pair(ipol, 0, self.mask))
+        }
+        Ok(())
+    }
+
+    pub fn write_bits(&mut self, value: u16) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        try!(bus.modify_pair(MCP23X17Register::OLAT(0), value & self.mask, !value & self.mask));
+        Ok(())
+    }
+
+    pub fn read_bits(&mut self) -> Result<u16> {
+        let mut bus = lock(&self.bus);
+        let val = try!(bus.read_pair(MCP23X17Register::GPIO(0)));
+        Ok(val & self.mask)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Logic, Logic3, DigitalRead, DigitalWrite, IoPin, OpenDrain, RegisterDesc};
+    use sys::Edge;
+    use super::super::{MCP23X17, MCP23X17Register, Simulator, Transport, Config};
+
+    fn setup() -> (Simulator, MCP23X17<Simulator>) {
+        let sim = Simulator::new();
+        let chip = MCP23X17::mcp23x17(sim.clone(), &Config::new()).unwrap();
+        (sim, chip)
+    }
+
+    #[test]
+    fn pin_input() {
+        let (sim, chip) = setup();
+        let mut pin = chip.portb().input(3).unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IODIR(1)), 0xFF);
+
+        pin.pull_up().unwrap();
+        assert_eq!(sim.register(MCP23X17Register::GPPU(1)), 0b1000);
+        pin.pull_off().unwrap();
+        assert_eq!(sim.register(MCP23X17Register::GPPU(1)), 0);
+
+        sim.set_inputs(1, 0b1000);
+        assert_eq!(pin.digital_read().unwrap(), Logic::High);
+        sim.set_inputs(1, 0b0111);
+        assert_eq!(pin.digital_read().unwrap(), Logic::Low);
+    }
+
+    #[test]
+    fn pin_output() {
+        let (sim, chip) = setup();
+        let mut pin = chip.porta().output(2).unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IODIR(0)), !0b100);
+
+        pin.high().unwrap();
+        assert_eq!(sim.outputs(0), 0b100);
+        pin.low().unwrap();
+        assert_eq!(sim.outputs(0), 0);
+
+        assert!(chip.porta().output(8).is_err());
+        assert!(chip.porta().input(8).is_err());
+    }
+
+    #[test]
+    fn pin_output_single_transaction() {
+        let (sim, chip) = setup();
+        let mut pin = chip.porta().output(0).unwrap();
+        // input pin reading differently must not leak into the latch
+        let _input = chip.porta().input(1).unwrap();
+        sim.set_inputs(0, 0b10);
+
+        let before = sim.transactions();
+        pin.high().unwrap();
+        assert_eq!(sim.transactions(), before + 1);
+        assert_eq!(sim.register(MCP23X17Register::OLAT(0)), 0b01);
+    }
+
+    #[test]
+    fn pin_output_drop_restores_input() {
+        let (sim, chip) = setup();
+        let pin = chip.porta().output(4).unwrap();
+        let _other = chip.porta().output(5).unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IODIR(0)), !0x30);
+        drop(pin);
+        assert_eq!(sim.register(MCP23X17Register::IODIR(0)), !0x20);
+    }
+
+    #[test]
+    fn io_pin() {
+        let (sim, chip) = setup();
+        let mut pin = chip.porta().io(5).unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IODIR(0)), 0xFF);
+
+        pin.write3(Logic3::High).unwrap();
+        assert_eq!(sim.outputs(0), 0x20);
+        pin.write3(Logic3::Low).unwrap();
+        assert_eq!(sim.outputs(0), 0);
+        assert_eq!(sim.register(MCP23X17Register::IODIR(0)), !0x20);
+        pin.write3(Logic3::Z).unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IODIR(0)), 0xFF);
+        sim.set_inputs(0, 0x20);
+        assert_eq!(pin.read().unwrap(), Logic::High);
+
+        // released line is pulled up from outside
+        let mut line = OpenDrain::new(pin).unwrap();
+        line.low().unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IODIR(0)), !0x20);
+        assert_eq!(sim.outputs(0), 0);
+        line.high().unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IODIR(0)), 0xFF);
+        assert_eq!(line.digital_read().unwrap(), Logic::High);
+
+        assert!(chip.porta().io(8).is_err());
+    }
+
+    #[test]
+    fn resync_after_reset() {
+        let (sim, chip) = setup();
+        let mut pin = chip.portb().output(0).unwrap();
+        pin.high().unwrap();
+
+        // chip lost its state behind our back
+        let mut raw = sim.clone();
+        Transport::write(&mut raw, MCP23X17Register::OLAT(1).offset() as u8, 0x80).unwrap();
+        chip.resync().unwrap();
+
+        pin.high().unwrap();
+        assert_eq!(sim.register(MCP23X17Register::OLAT(1)), 0x81);
+    }
+
+    #[test]
+    fn input_invert() {
+        let (sim, chip) = setup();
+        let mut pin = chip.porta().input(1).unwrap();
+        let mut group = chip.porta().group_input(0xF0).unwrap();
+        pin.invert(true).unwrap();
+        group.invert(true).unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IPOL(0)), 0xF2);
+
+        sim.set_inputs(0, 0x30);
+        assert_eq!(pin.digital_read().unwrap(), Logic::High);
+        assert_eq!(group.digital_read().unwrap(), 0xC0);
+
+        group.invert(false).unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IPOL(0)), 0x02);
+    }
+
+    #[test]
+    fn bank_mode() {
+        let (sim, chip) = setup();
+        let mut config = Config::new();
+        config.bank(true).interrupt_mirror(true);
+        chip.configure(&config).unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IOCON(0)), 0xE8);
+
+        let mut pin = chip.portb().output(5).unwrap();
+        pin.high().unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IODIR(1)), !0x20);
+        assert_eq!(sim.outputs(1), 0x20);
+
+        let mut bus = chip.bus16(0x00FF);
+        bus.set_input().unwrap();
+        sim.set_inputs(0, 0x5A);
+        assert_eq!(bus.read_bits().unwrap(), 0x5A);
+
+        // back to BANK=0 through the BANK=1 address of IOCON
+        chip.configure(&Config::new()).unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IOCON(0)), 0x28);
+        pin.low().unwrap();
+        assert_eq!(sim.outputs(1), 0);
+    }
+
+    #[test]
+    fn mcp23x08() {
+        let sim = Simulator::mcp23x08();
+        let mut config = Config::new();
+        config.interrupt_mirror(true).bank(true);
+        let chip = MCP23X17::mcp23x08(sim.clone(), &config).unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IOCON(0)), 0x28);
+
+        let mut pin = chip.porta().output(6).unwrap();
+        pin.high().unwrap();
+        assert_eq!(sim.outputs(0), 0x40);
+        assert!(chip.portb().output(6).is_err());
+        assert!(chip.bus16(0xFFFF).set_output().is_err());
+
+        let mut group = chip.porta().group_input(0x0F).unwrap();
+        group.interrupt(Edge::FallingEdge).unwrap();
+        sim.set_inputs(0, 0x0F);
+        sim.set_inputs(0, 0x0E);
+        let events = chip.interrupt_handler().handle().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].port, events[0].pin, events[0].level), (0, 0, Logic::Low));
+    }
+
+    #[test]
+    fn group_input() {
+        let (sim, chip) = setup();
+        let mut group = chip.porta().group_input(0x0F).unwrap();
+
+        group.pull_up().unwrap();
+        assert_eq!(sim.register(MCP23X17Register::GPPU(0)), 0x0F);
+        group.pull_off().unwrap();
+        assert_eq!(sim.register(MCP23X17Register::GPPU(0)), 0);
+
+        sim.set_inputs(0, 0xA5);
+        assert_eq!(group.digital_read().unwrap(), 0x05);
+    }
+
+    #[test]
+    fn group_output() {
+        let (sim, chip) = setup();
+        let mut group = chip.portb().group_output(0xF0).unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IODIR(1)), 0x0F);
+
+        group.high().unwrap();
+        assert_eq!(sim.outputs(1), 0xF0);
+        group.low().unwrap();
+        assert_eq!(sim.outputs(1), 0);
+
+        drop(group);
+        assert_eq!(sim.register(MCP23X17Register::IODIR(1)), 0xFF);
+    }
+
+    #[test]
+    fn group_output_write_bits() {
+        let (sim, chip) = setup();
+        let mut group = chip.porta().group_output(0x0F).unwrap();
+        let mut other = chip.porta().output(7).unwrap();
+        other.high().unwrap();
+
+        let before = sim.transactions();
+        group.write_bits(0b1010_0101).unwrap();
+        assert_eq!(sim.transactions(), before + 1);
+        assert_eq!(sim.outputs(0), 0b1000_0101);
+    }
+
+    #[test]
+    fn bus16() {
+        let (sim, chip) = setup();
+        let mut bus = chip.bus16(0xFFFF);
+        bus.set_output().unwrap();
+        assert_eq!(sim.register(MCP23X17Register::IODIR(0)), 0);
+        assert_eq!(sim.register(MCP23X17Register::IODIR(1)), 0);
+
+        let before = sim.transactions();
+        bus.write_bits(0xBEEF).unwrap();
+        assert_eq!(sim.transactions(), before + 1);
+        assert_eq!(sim.outputs(0), 0xEF);
+        assert_eq!(sim.outputs(1), 0xBE);
+
+        bus.set_input().unwrap();
+        sim.set_inputs(0, 0x34);
+        sim.set_inputs(1, 0x12);
+        let before = sim.transactions();
+        assert_eq!(bus.read_bits().unwrap(), 0x1234);
+        assert_eq!(sim.transactions(), before + 1);
+    }
+
+    #[test]
+    fn group_interrupt_both_edges() {
+        let (sim, chip) = setup();
+        let mut group = chip.porta().group_input(0x03).unwrap();
+        group.interrupt(Edge::BothEdges).unwrap();
+        assert_eq!(sim.register(MCP23X17Register::GPINTEN(0)), 0x03);
+        assert_eq!(sim.register(MCP23X17Register::INTCON(0)), 0);
+
+        sim.set_inputs(0, 0x02);
+        assert!(sim.interrupt(0));
+        assert_eq!(sim.register(MCP23X17Register::INTF(0)), 0x02);
+        assert_eq!(group.digital_read().unwrap(), 0x02);
+        assert!(!sim.interrupt(0));
+
+        // masked pins do not interrupt
+        sim.set_inputs(0, 0x06);
+        assert!(!sim.interrupt(0));
+
+        group.stop_interrupt().unwrap();
+        assert_eq!(sim.register(MCP23X17Register::GPINTEN(0)), 0);
+    }
+}
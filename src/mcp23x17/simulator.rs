@@ -0,0 +1,250 @@
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use RegisterDesc;
+use super::{MCP23X17Register, Transport};
+
+const REGISTERS: usize = 22;
+
+const IOCON_SEQOP: u8 = 0x20;
+const IOCON_MIRROR: u8 = 0x40;
+const IOCON_BANK: u8 = 0x80;
+
+struct State {
+    regs: [u8; REGISTERS],
+    inputs: [u8; 2],
+    ports: usize,
+    connected: bool,
+    errors: usize,
+    transactions: usize
+}
+
+// In-memory MCP23x17 implementing Transport. Registers are kept in the
+// BANK=0 order, addresses on the transport follow IOCON.BANK.
+// The MCP23x08 variant has only port 0 and always uses the BANK=1 map.
+// Clones share the chip, so a test can keep one to drive inputs and inspect registers.
+#[derive(Clone)]
+pub struct Simulator(Arc<Mutex<State>>);
+
+fn offset(reg: MCP23X17Register) -> usize {
+    reg.offset()
+}
+
+fn power_on() -> [u8; REGISTERS] {
+    let mut regs = [0u8; REGISTERS];
+    // all pins are inputs
+    regs[offset(MCP23X17Register::IODIR(0))] = 0xFF;
+    regs[offset(MCP23X17Register::IODIR(1))] = 0xFF;
+    regs
+}
+
+impl Simulator {
+    pub fn new() -> Simulator {
+        Simulator::with_ports(2)
+    }
+
+    pub fn mcp23x08() -> Simulator {
+        Simulator::with_ports(1)
+    }
+
+    fn with_ports(ports: usize) -> Simulator {
+        Simulator(Arc::new(Mutex::new(State {
+            regs: power_on(),
+            inputs: [0, 0],
+            ports: ports,
+            connected: true,
+            errors: 0,
+            transactions: 0
+        })))
+    }
+
+    // Chip reset (RESET pin or brown-out), registers back to power-on values
+    pub fn reset(&self) {
+        self.state().regs = power_on();
+    }
+
+    // Unplugged chip ignores writes and reads as 0xFF like a floating MISO
+    pub fn set_connected(&self, connected: bool) {
+        self.state().connected = connected;
+    }
+
+    // The next count transactions fail with a bus error and do nothing
+    pub fn fail_next(&self, count: usize) {
+        self.state().errors = count;
+    }
+
+    fn state(&self) -> MutexGuard<State> {
+        match self.0.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    pub fn register(&self, reg: MCP23X17Register) -> u8 {
+        self.state().regs[offset(reg)]
+    }
+
+    // Levels driven on the pins from outside, raises interrupts like the chip does
+    pub fn set_inputs(&self, port: usize, value: u8) {
+        let mut state = self.state();
+        let before = state.gpio(port);
+        state.inputs[port] = value;
+        let after = state.gpio(port);
+
+        let enabled = state.regs[offset(MCP23X17Register::GPINTEN(port))] & state.regs[offset(MCP23X17Register::IODIR(port))];
+        let intcon = state.regs[offset(MCP23X17Register::INTCON(port))];
+        let defval = state.regs[offset(MCP23X17Register::DEFVAL(port))];
+        let fired = enabled & ((intcon & (after ^ defval)) | (!intcon & (after ^ before)));
+        if fired != 0 {
+            if state.regs[offset(MCP23X17Register::INTF(port))] == 0 {
+                state.regs[offset(MCP23X17Register::INTCAP(port))] = after;
+            }
+            state.regs[offset(MCP23X17Register::INTF(port))] |= fired;
+        }
+    }
+
+    // Level of the pins driven by the chip
+    pub fn outputs(&self, port: usize) -> u8 {
+        let state = self.state();
+        state.regs[offset(MCP23X17Register::OLAT(port))] & !state.regs[offset(MCP23X17Register::IODIR(port))]
+    }
+
+    // INTA/INTB pin asserted (active state, regardless of INTPOL)
+    pub fn interrupt(&self, port: usize) -> bool {
+        let state = self.state();
+        let inta = state.regs[offset(MCP23X17Register::INTF(0))] != 0;
+        let intb = state.regs[offset(MCP23X17Register::INTF(1))] != 0;
+        if state.regs[offset(MCP23X17Register::IOCON(0))] & IOCON_MIRROR != 0 {
+            inta || intb
+        } else if port == 0 {
+            inta
+        } else {
+            intb
+        }
+    }
+
+    // Number of transport calls (one per SPI/I2C transaction)
+    pub fn transactions(&self) -> usize {
+        self.state().transactions
+    }
+}
+
+impl State {
+    fn gpio(&self, port: usize) -> u8 {
+        let iodir = self.regs[offset(MCP23X17Register::IODIR(port))];
+        let ipol = self.regs[offset(MCP23X17Register::IPOL(port))];
+        let olat = self.regs[offset(MCP23X17Register::OLAT(port))];
+        ((self.inputs[port] ^ ipol) & iodir) | (olat & !iodir)
+    }
+
+    fn iocon(&self) -> u8 {
+        let iocon = self.regs[offset(MCP23X17Register::IOCON(0))];
+        if self.ports == 1 { iocon | IOCON_BANK } else { iocon }
+    }
+
+    // register behind a transport address
+    fn index(&self, addr: usize) -> Option<usize> {
+        if self.iocon() & IOCON_BANK == 0 {
+            if addr < REGISTERS { Some(addr) } else { None }
+        } else {
+            let (port, reg) = (addr >> 4, addr & 0x0F);
+            if port < self.ports && reg < REGISTERS / 2 { Some(reg * 2 + port) } else { None }
+        }
+    }
+
+    fn next(&self, addr: usize) -> usize {
+        let iocon = self.iocon();
+        match (iocon & IOCON_BANK != 0, iocon & IOCON_SEQOP != 0) {
+            // byte mode toggles between the A/B pair
+            (false, true) => addr ^ 1,
+            (false, false) => (addr + 1) % REGISTERS,
+            // byte mode keeps the address
+            (true, true) => addr,
+            (true, false) => match addr {
+                0x0A if self.ports == 1 => 0x00,
+                0x0A => 0x10,
+                0x1A => 0x00,
+                _ => addr + 1
+            }
+        }
+    }
+
+    fn read(&mut self, reg: usize) -> u8 {
+        let port = reg & 1;
+        if reg == offset(MCP23X17Register::GPIO(port)) {
+            self.regs[offset(MCP23X17Register::INTF(port))] = 0;
+            self.gpio(port)
+        } else {
+            if reg == offset(MCP23X17Register::INTCAP(port)) {
+                self.regs[offset(MCP23X17Register::INTF(port))] = 0;
+            }
+            self.regs[reg]
+        }
+    }
+
+    fn write(&mut self, reg: usize, value: u8) {
+        let port = reg & 1;
+        if reg == offset(MCP23X17Register::GPIO(port)) || reg == offset(MCP23X17Register::OLAT(port)) {
+            self.regs[offset(MCP23X17Register::OLAT(port))] = value;
+        } else if reg == offset(MCP23X17Register::IOCON(port)) {
+            // IOCON is shared by both ports, MCP23x08 lacks BANK and MIRROR
+            let value = if self.ports == 1 { value & !(IOCON_BANK | IOCON_MIRROR) } else { value };
+            self.regs[offset(MCP23X17Register::IOCON(0))] = value;
+            self.regs[offset(MCP23X17Register::IOCON(1))] = value;
+        } else if reg == offset(MCP23X17Register::INTF(port)) || reg == offset(MCP23X17Register::INTCAP(port)) {
+            // read-only
+        } else {
+            self.regs[reg] = value;
+        }
+    }
+}
+
+fn no_such_register() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "no such register")
+}
+
+fn bus_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "bus error")
+}
+
+impl Transport for Simulator {
+    fn read_burst(&mut self, reg: u8, buf: &mut [u8]) -> io::Result<()> {
+        let mut state = self.state();
+        state.transactions += 1;
+        if state.errors > 0 {
+            state.errors -= 1;
+            return Err(bus_error());
+        }
+        if !state.connected {
+            for byte in buf.iter_mut() {
+                *byte = 0xFF;
+            }
+            return Ok(());
+        }
+        let mut addr = reg as usize;
+        for byte in buf.iter_mut() {
+            let reg = try!(state.index(addr).ok_or(no_such_register()));
+            *byte = state.read(reg);
+            addr = state.next(addr);
+        }
+        Ok(())
+    }
+
+    fn write_burst(&mut self, reg: u8, data: &[u8]) -> io::Result<()> {
+        let mut state = self.state();
+        state.transactions += 1;
+        if state.errors > 0 {
+            state.errors -= 1;
+            return Err(bus_error());
+        }
+        if !state.connected {
+            return Ok(());
+        }
+        let mut addr = reg as usize;
+        for &byte in data {
+            let reg = try!(state.index(addr).ok_or(no_such_register()));
+            state.write(reg, byte);
+            addr = state.next(addr);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use std::io;
+use spi::{SpiBus, SpiDevice, SpiConfig, SpiModeFlags, Segment, SPI_MODE_0};
+use {Result, Error};
+use super::{MCP23X17, Transport, Config};
+
+const CMD_WRITE: usize = 0x40;
+const CMD_READ: usize  = 0x41;
+
+// One chip on a (possibly shared) SPI device, selected by hardware address
+pub struct SpiTransport {
+    dev: Box<SpiDevice>,
+    address: usize
+}
+
+impl SpiTransport {
+    pub fn new<D: SpiDevice + 'static>(dev: D, address: usize) -> SpiTransport {
+        SpiTransport { dev: Box::new(dev), address: address }
+    }
+}
+
+impl Transport for SpiTransport {
+    fn read_burst(&mut self, reg: u8, buf: &mut [u8]) -> io::Result<()> {
+        let cmd = [(CMD_READ | (self.address << 1)) as u8, reg];
+        self.dev.transaction(&mut [Segment::write(&cmd), Segment::read(buf)])
+    }
+
+    fn write_burst(&mut self, reg: u8, data: &[u8]) -> io::Result<()> {
+        let cmd = [(CMD_WRITE | (self.address << 1)) as u8, reg];
+        self.dev.transaction(&mut [Segment::write(&cmd), Segment::write(data)])
+    }
+
+    fn write_unaddressed(&mut self, reg: u8, value: u8) -> io::Result<()> {
+        let cmd = [CMD_WRITE as u8, reg, value];
+        self.dev.transaction(&mut [Segment::write(&cmd)])
+    }
+}
+
+// A2..A0 on MCP23S17, A1..A0 on MCP23S08
+fn hardware_address(address: usize, ports: usize) -> Result<usize> {
+    if address > 7 || (ports == 1 && address > 3) {
+        return Err(Error::InvalidAddress);
+    }
+    Ok(address)
+}
+
+pub type MCP23S17 = MCP23X17<SpiTransport>;
+
+impl MCP23X17<SpiTransport> {
+    // Chip at hardware address 0-7 on /dev/spidev0.0
+    pub unsafe fn new(address: usize) -> Result<MCP23S17> {
+        MCP23S17::builder().address(address).open()
+    }
+
+    pub fn builder() -> MCP23S17Builder {
+        MCP23S17Builder {
+            path: "/dev/spidev0.0".to_string(),
+            speed: 4_000_000,
+            mode: SPI_MODE_0,
+            address: 0,
+            config: Config::new()
+        }
+    }
+}
+
+pub struct MCP23S17Builder {
+    path: String,
+    speed: u32,
+    mode: SpiModeFlags,
+    address: usize,
+    config: Config
+}
+
+impl MCP23S17Builder {
+    pub fn path<P: Into<String>>(&mut self, path: P) -> &mut Self {
+        self.path = path.into(); self
+    }
+
+    pub fn speed(&mut self, hz: u32) -> &mut Self {
+        self.speed = hz; self
+    }
+
+    pub fn mode(&mut self, mode: SpiModeFlags) -> &mut Self {
+        self.mode = mode; self
+    }
+
+    pub fn address(&mut self, address: usize) -> &mut Self {
+        self.address = address; self
+    }
+
+    pub fn config(&mut self, config: Config) -> &mut Self {
+        self.config = config; self
+    }
+
+    pub unsafe fn open(&self) -> Result<MCP23S17> {
+        let bus = try!(self.open_bus());
+        bus.chip_with_config(self.address, &self.config)
+    }
+
+    // 8-bit MCP23S08 at hardware address 0-3
+    pub unsafe fn open_mcp23s08(&self) -> Result<MCP23S17> {
+        let bus = try!(self.open_bus());
+        bus.mcp23s08_with_config(self.address, &self.config)
+    }
+
+    // Shared spidev handle for several chips on one chip select (HAEN),
+    // MCP23S17 and MCP23S08 can be mixed
+    pub unsafe fn open_bus(&self) -> Result<MCP23S17Bus> {
+        let bus = try!(SpiBus::open(&self.path));
+        Ok(MCP23S17Bus::new(&bus, self.speed, self.mode))
+    }
+}
+
+#[derive(Clone)]
+pub struct MCP23S17Bus {
+    bus: SpiBus,
+    config: SpiConfig
+}
+
+impl MCP23S17Bus {
+    // Chips on an SPI bus also used by other devices
+    pub fn new(bus: &SpiBus, speed: u32, mode: SpiModeFlags) -> MCP23S17Bus {
+        let mut config = SpiConfig::new();
+        config.speed(speed).mode(mode);
+        MCP23S17Bus { bus: bus.clone(), config: config }
+    }
+
+    pub fn chip(&self, address: usize) -> Result<MCP23S17> {
+        self.chip_with_config(address, &Config::new())
+    }
+
+    pub fn chip_with_config(&self, address: usize, config: &Config) -> Result<MCP23S17> {
+        let address = try!(hardware_address(address, 2));
+        MCP23X17::mcp23x17(SpiTransport::new(self.bus.device(&self.config), address), config)
+    }
+
+    pub fn mcp23s08(&self, address: usize) -> Result<MCP23S17> {
+        self.mcp23s08_with_config(address, &Config::new())
+    }
+
+    pub fn mcp23s08_with_config(&self, address: usize, config: &Config) -> Result<MCP23S17> {
+        let address = try!(hardware_address(address, 1));
+        MCP23X17::mcp23x08(SpiTransport::new(self.bus.device(&self.config), address), config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use DigitalWrite;
+    use spi::MockSpiDevice;
+    use super::super::{MCP23X17, Config};
+    use super::SpiTransport;
+
+    #[test]
+    fn mcp23s17_on_mock_device() {
+        let mock = MockSpiDevice::new();
+        // IOCON read back, then the registers of the resync
+        mock.respond(&[0x28]);
+        mock.respond(&[0xFF; 16]);
+        let chip = MCP23X17::mcp23x17(SpiTransport::new(mock.clone(), 3), &Config::new()).unwrap();
+        // HAEN is clear after power-up, the chip only answers address 0
+        assert_eq!(mock.written()[0], vec![0x40, 0x0A, 0x08]);
+        assert_eq!(mock.written()[1], vec![0x46, 0x0A, 0x28]);
+        assert_eq!(mock.written()[2], vec![0x47, 0x0A, 0x00]);
+
+        let mut pin = chip.portb().output(1).unwrap();
+        pin.low().unwrap();
+        let written = mock.written();
+        assert_eq!(written[written.len() - 2], vec![0x46, 0x01, 0xFD]);
+        assert_eq!(written[written.len() - 1], vec![0x46, 0x15, 0xFD]);
+    }
+}
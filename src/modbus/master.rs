@@ -0,0 +1,131 @@
+use std::io;
+use std::time::Duration;
+use {DigitalWrite, Result, Error};
+use super::{Rtu, pack_bits, unpack_bits, pack_registers, unpack_registers};
+use super::{READ_COILS, READ_DISCRETE_INPUTS, READ_HOLDING_REGISTERS, READ_INPUT_REGISTERS,
+            WRITE_SINGLE_COIL, WRITE_SINGLE_REGISTER, WRITE_MULTIPLE_COILS, WRITE_MULTIPLE_REGISTERS,
+            MAX_READ_BITS, MAX_READ_REGISTERS, MAX_WRITE_BITS, MAX_WRITE_REGISTERS};
+
+fn invalid_data(msg: &str) -> Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg).into()
+}
+
+fn check_quantity(count: usize, max: usize) -> Result<()> {
+    if count == 0 || count > max {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "quantity out of range").into());
+    }
+    Ok(())
+}
+
+// Modbus RTU client. Unit 0 broadcasts writes, no response is read.
+pub struct RtuMaster<D> {
+    rtu: Rtu<D>,
+    timeout: Duration
+}
+
+impl<D: DigitalWrite> RtuMaster<D> {
+    pub fn new(rtu: Rtu<D>) -> RtuMaster<D> {
+        RtuMaster { rtu: rtu, timeout: Duration::from_secs(1) }
+    }
+
+    // Time for the slave to start responding
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout; self
+    }
+
+    pub fn into_inner(self) -> Rtu<D> {
+        self.rtu
+    }
+
+    // Response data after the function code
+    fn request(&mut self, unit: u8, pdu: &[u8]) -> Result<Vec<u8>> {
+        let mut frame = Vec::with_capacity(pdu.len() + 1);
+        frame.push(unit);
+        frame.extend_from_slice(pdu);
+        // stale bytes of an earlier, timed out response
+        try!(self.rtu.serial().discard());
+        try!(self.rtu.send(&frame));
+        if unit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let response = try!(self.rtu.receive_frame(false, Some(self.timeout)));
+        if response[0] != unit || response[1] & 0x7F != pdu[0] {
+            return Err(invalid_data("response does not match the request"));
+        }
+        if response[1] & 0x80 != 0 {
+            return Err(Error::ModbusException(response[2]));
+        }
+        Ok(response[2..].to_vec())
+    }
+
+    fn read_bits(&mut self, function: u8, unit: u8, address: u16, count: usize) -> Result<Vec<bool>> {
+        try!(check_quantity(count, MAX_READ_BITS));
+        let data = try!(self.request(unit, &[function, (address >> 8) as u8, address as u8, (count >> 8) as u8, count as u8]));
+        if data.len() != 1 + (count + 7) / 8 || data[0] as usize != data.len() - 1 {
+            return Err(invalid_data("wrong byte count"));
+        }
+        Ok(unpack_bits(&data[1..], count))
+    }
+
+    fn read_registers(&mut self, function: u8, unit: u8, address: u16, count: usize) -> Result<Vec<u16>> {
+        try!(check_quantity(count, MAX_READ_REGISTERS));
+        let data = try!(self.request(unit, &[function, (address >> 8) as u8, address as u8, (count >> 8) as u8, count as u8]));
+        if data.len() != 1 + count * 2 || data[0] as usize != data.len() - 1 {
+            return Err(invalid_data("wrong byte count"));
+        }
+        Ok(unpack_registers(&data[1..]))
+    }
+
+    // Write responses echo address and value or quantity
+    fn write(&mut self, unit: u8, pdu: &[u8]) -> Result<()> {
+        let data = try!(self.request(unit, pdu));
+        if unit != 0 && data[..] != pdu[1..5] {
+            return Err(invalid_data("write not confirmed"));
+        }
+        Ok(())
+    }
+
+    pub fn read_coils(&mut self, unit: u8, address: u16, count: usize) -> Result<Vec<bool>> {
+        self.read_bits(READ_COILS, unit, address, count)
+    }
+
+    pub fn read_discrete_inputs(&mut self, unit: u8, address: u16, count: usize) -> Result<Vec<bool>> {
+        self.read_bits(READ_DISCRETE_INPUTS, unit, address, count)
+    }
+
+    pub fn read_holding_registers(&mut self, unit: u8, address: u16, count: usize) -> Result<Vec<u16>> {
+        self.read_registers(READ_HOLDING_REGISTERS, unit, address, count)
+    }
+
+    pub fn read_input_registers(&mut self, unit: u8, address: u16, count: usize) -> Result<Vec<u16>> {
+        self.read_registers(READ_INPUT_REGISTERS, unit, address, count)
+    }
+
+    pub fn write_single_coil(&mut self, unit: u8, address: u16, value: bool) -> Result<()> {
+        let value = if value { 0xFF } else { 0x00 };
+        self.write(unit, &[WRITE_SINGLE_COIL, (address >> 8) as u8, address as u8, value, 0x00])
+    }
+
+    pub fn write_single_register(&mut self, unit: u8, address: u16, value: u16) -> Result<()> {
+        self.write(unit, &[WRITE_SINGLE_REGISTER, (address >> 8) as u8, address as u8, (value >> 8) as u8, value as u8])
+    }
+
+    pub fn write_multiple_coils(&mut self, unit: u8, address: u16, values: &[bool]) -> Result<()> {
+        try!(check_quantity(values.len(), MAX_WRITE_BITS));
+        let bytes = pack_bits(values);
+        let count = values.len();
+        let mut pdu = vec![WRITE_MULTIPLE_COILS, (address >> 8) as u8, address as u8, (count >> 8) as u8, count as u8, bytes.len() as u8];
+        pdu.extend_from_slice(&bytes);
+        self.write(unit, &pdu)
+    }
+
+    pub fn write_multiple_registers(&mut self, unit: u8, address: u16, values: &[u16]) -> Result<()> {
+        try!(check_quantity(values.len(), MAX_WRITE_REGISTERS));
+        let bytes = pack_registers(values);
+        let count = values.len();
+        let mut pdu = vec![WRITE_MULTIPLE_REGISTERS, (address >> 8) as u8, address as u8, (count >> 8) as u8, count as u8, bytes.len() as u8];
+        pdu.extend_from_slice(&bytes);
+        self.write(unit, &pdu)
+    }
+}
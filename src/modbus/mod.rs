@@ -0,0 +1,92 @@
+use std::result;
+
+mod rtu;
+mod master;
+mod slave;
+
+pub use self::rtu::{Rtu, NoPin};
+pub use self::master::RtuMaster;
+pub use self::slave::{RtuSlave, SlaveHandler, DataMap};
+
+pub const READ_COILS: u8 = 0x01;
+pub const READ_DISCRETE_INPUTS: u8 = 0x02;
+pub const READ_HOLDING_REGISTERS: u8 = 0x03;
+pub const READ_INPUT_REGISTERS: u8 = 0x04;
+pub const WRITE_SINGLE_COIL: u8 = 0x05;
+pub const WRITE_SINGLE_REGISTER: u8 = 0x06;
+pub const WRITE_MULTIPLE_COILS: u8 = 0x0F;
+pub const WRITE_MULTIPLE_REGISTERS: u8 = 0x10;
+
+// Quantity limits of the spec, they keep a frame within 256 bytes
+pub const MAX_READ_BITS: usize = 2000;
+pub const MAX_READ_REGISTERS: usize = 125;
+pub const MAX_WRITE_BITS: usize = 1968;
+pub const MAX_WRITE_REGISTERS: usize = 123;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Exception {
+    IllegalFunction = 0x01,
+    IllegalDataAddress = 0x02,
+    IllegalDataValue = 0x03,
+    ServerDeviceFailure = 0x04,
+    Acknowledge = 0x05,
+    ServerDeviceBusy = 0x06
+}
+
+pub type ExceptionResult<T> = result::Result<T, Exception>;
+
+// CRC-16/MODBUS, sent low byte first
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { crc >> 1 ^ 0xA001 } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    let mut bytes = vec![0u8; (bits.len() + 7) / 8];
+    for (i, &bit) in bits.iter().enumerate() {
+        if bit {
+            bytes[i / 8] |= 1 << (i % 8);
+        }
+    }
+    bytes
+}
+
+fn unpack_bits(bytes: &[u8], count: usize) -> Vec<bool> {
+    (0..count).map(|i| bytes[i / 8] & (1 << (i % 8)) != 0).collect()
+}
+
+fn pack_registers(registers: &[u16]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(registers.len() * 2);
+    for &value in registers {
+        bytes.push((value >> 8) as u8);
+        bytes.push(value as u8);
+    }
+    bytes
+}
+
+fn unpack_registers(bytes: &[u8]) -> Vec<u16> {
+    bytes.chunks(2).map(|pair| (pair[0] as u16) << 8 | pair[1] as u16).collect()
+}
+
+fn word(bytes: &[u8]) -> u16 {
+    (bytes[0] as u16) << 8 | bytes[1] as u16
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crc16, pack_bits, unpack_bits};
+
+    #[test]
+    fn crc_and_bits() {
+        assert_eq!(crc16(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0A]), 0xCDC5);
+        let bits = [true, false, true, true, false, false, true, false, true];
+        assert_eq!(pack_bits(&bits), vec![0x4D, 0x01]);
+        assert_eq!(unpack_bits(&[0x4D, 0x01], 9), bits.to_vec());
+    }
+}
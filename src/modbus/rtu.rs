@@ -0,0 +1,166 @@
+use std::io::prelude::*;
+use std::io;
+use std::time::Duration;
+use serial::Serial;
+use {DigitalWrite, DigitalLogic, Logic, Result, delay_usec, monotonic};
+use super::{crc16, WRITE_SINGLE_COIL, WRITE_SINGLE_REGISTER, WRITE_MULTIPLE_COILS, WRITE_MULTIPLE_REGISTERS};
+
+// Direction pin of a transceiver that switches by itself (or RS-232)
+pub struct NoPin;
+
+impl DigitalWrite for NoPin {
+    fn digital_write<L: DigitalLogic>(&mut self, _level: L) -> Result<()> {
+        Ok(())
+    }
+}
+
+// RTU framing on a serial port: CRC, silent interval between frames and
+// the DE/RE pin of an RS-485 transceiver (high while sending)
+pub struct Rtu<D> {
+    serial: Serial,
+    de: D,
+    // 3.5 character times
+    t35: Duration,
+    byte_timeout: Duration,
+    last: Duration
+}
+
+impl Rtu<NoPin> {
+    pub fn new(serial: Serial) -> Rtu<NoPin> {
+        Rtu::build(serial, NoPin)
+    }
+}
+
+impl<D: DigitalWrite> Rtu<D> {
+    pub fn rs485(serial: Serial, mut de: D) -> Result<Rtu<D>> {
+        // receive
+        try!(de.low());
+        Ok(Rtu::build(serial, de))
+    }
+
+    fn build(serial: Serial, de: D) -> Rtu<D> {
+        let baud = serial.baud().max(1) as u64;
+        // fixed 1.75 ms above 19200 baud as the spec recommends, 11 bits a character
+        let t35 = if baud > 19200 { 1750 } else { 38_500_000 / baud };
+        Rtu {
+            serial: serial,
+            de: de,
+            t35: Duration::from_micros(t35),
+            // the kernel and USB adapters deliver bytes in bursts, t1.5 can't be kept
+            byte_timeout: Duration::from_micros(t35).max(Duration::from_millis(50)),
+            last: Duration::from_secs(0)
+        }
+    }
+
+    // Longest gap between the bytes of one frame
+    pub fn byte_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.byte_timeout = timeout; self
+    }
+
+    pub fn serial(&mut self) -> &mut Serial {
+        &mut self.serial
+    }
+
+    pub fn into_inner(self) -> (Serial, D) {
+        (self.serial, self.de)
+    }
+
+    // Appends the CRC
+    pub(crate) fn send(&mut self, frame: &[u8]) -> Result<()> {
+        let crc = crc16(frame);
+        let mut tx = Vec::with_capacity(frame.len() + 2);
+        tx.extend_from_slice(frame);
+        tx.push(crc as u8);
+        tx.push((crc >> 8) as u8);
+
+        let idle = monotonic() - self.last;
+        if idle < self.t35 {
+            let wait = self.t35 - idle;
+            delay_usec(wait.as_secs() * 1_000_000 + wait.subsec_nanos() as u64 / 1000);
+        }
+
+        try!(self.de.digital_write(Logic::High));
+        let sent = self.serial.write_all(&tx).and_then(|_| self.serial.flush());
+        // back to receive even when the write failed
+        try!(self.de.digital_write(Logic::Low));
+        self.last = monotonic();
+        Ok(try!(sent))
+    }
+
+    // The port keeps the timeout it was configured with
+    fn receive(&mut self, buf: &mut Vec<u8>, count: usize, timeout: Option<Duration>) -> io::Result<()> {
+        let saved = self.serial.timeout();
+        let result = self.receive_bytes(buf, count, timeout);
+        self.serial.set_timeout(saved);
+        result
+    }
+
+    fn receive_bytes(&mut self, buf: &mut Vec<u8>, count: usize, timeout: Option<Duration>) -> io::Result<()> {
+        let mut timeout = timeout;
+        let end = buf.len() + count;
+        while buf.len() < end {
+            self.serial.set_timeout(timeout);
+            let mut byte = [0u8];
+            if try!(self.serial.read(&mut byte)) == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "serial port closed"));
+            }
+            buf.push(byte[0]);
+            timeout = Some(self.byte_timeout);
+        }
+        self.last = monotonic();
+        Ok(())
+    }
+
+    // Reads until the line is silent, after a broken frame
+    pub(crate) fn discard(&mut self) {
+        let mut buf = [0u8; 64];
+        let saved = self.serial.timeout();
+        self.serial.set_timeout(Some(self.byte_timeout));
+        while let Ok(n) = self.serial.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+        }
+        self.serial.set_timeout(saved);
+        self.last = monotonic();
+    }
+
+    // Frame without CRC. The length follows from the function code, requests
+    // and responses differ. timeout is for the first byte.
+    pub(crate) fn receive_frame(&mut self, request: bool, timeout: Option<Duration>) -> io::Result<Vec<u8>> {
+        let mut frame = Vec::with_capacity(256);
+        try!(self.receive(&mut frame, 2, timeout));
+        let function = frame[1];
+        let rest = if function & 0x80 != 0 {
+            1
+        } else {
+            match (function, request) {
+                (0x01..=0x06, true) => 4,
+                (0x01..=0x04, false) => {
+                    try!(self.receive(&mut frame, 1, Some(self.byte_timeout)));
+                    frame[2] as usize
+                },
+                (WRITE_SINGLE_COIL, false) | (WRITE_SINGLE_REGISTER, false) |
+                (WRITE_MULTIPLE_COILS, false) | (WRITE_MULTIPLE_REGISTERS, false) => 4,
+                (WRITE_MULTIPLE_COILS, true) | (WRITE_MULTIPLE_REGISTERS, true) => {
+                    try!(self.receive(&mut frame, 5, Some(self.byte_timeout)));
+                    frame[6] as usize
+                },
+                _ => {
+                    self.discard();
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported function code"));
+                }
+            }
+        };
+        try!(self.receive(&mut frame, rest + 2, Some(self.byte_timeout)));
+
+        let len = frame.len() - 2;
+        let crc = frame[len] as u16 | (frame[len + 1] as u16) << 8;
+        if crc != crc16(&frame[..len]) {
+            self.discard();
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "CRC mismatch"));
+        }
+        frame.truncate(len);
+        Ok(frame)
+    }
+}
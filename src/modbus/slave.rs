@@ -0,0 +1,419 @@
+use std::io;
+use std::time::Duration;
+use {DigitalRead, DigitalWrite, Logic, Result, Error};
+use super::{Rtu, Exception, ExceptionResult, pack_bits, unpack_bits, pack_registers, unpack_registers, word};
+use super::{READ_COILS, READ_DISCRETE_INPUTS, READ_HOLDING_REGISTERS, READ_INPUT_REGISTERS,
+            WRITE_SINGLE_COIL, WRITE_SINGLE_REGISTER, WRITE_MULTIPLE_COILS, WRITE_MULTIPLE_REGISTERS,
+            MAX_READ_BITS, MAX_READ_REGISTERS, MAX_WRITE_BITS, MAX_WRITE_REGISTERS};
+
+// Data model of a slave, unimplemented tables answer IllegalFunction
+pub trait SlaveHandler {
+    fn read_coils(&mut self, _address: u16, _count: usize) -> ExceptionResult<Vec<bool>> {
+        Err(Exception::IllegalFunction)
+    }
+
+    fn read_discrete_inputs(&mut self, _address: u16, _count: usize) -> ExceptionResult<Vec<bool>> {
+        Err(Exception::IllegalFunction)
+    }
+
+    fn read_holding_registers(&mut self, _address: u16, _count: usize) -> ExceptionResult<Vec<u16>> {
+        Err(Exception::IllegalFunction)
+    }
+
+    fn read_input_registers(&mut self, _address: u16, _count: usize) -> ExceptionResult<Vec<u16>> {
+        Err(Exception::IllegalFunction)
+    }
+
+    fn write_coils(&mut self, _address: u16, _values: &[bool]) -> ExceptionResult<()> {
+        Err(Exception::IllegalFunction)
+    }
+
+    fn write_registers(&mut self, _address: u16, _values: &[u16]) -> ExceptionResult<()> {
+        Err(Exception::IllegalFunction)
+    }
+}
+
+// Response PDU for a request PDU
+fn respond<H: SlaveHandler>(handler: &mut H, pdu: &[u8]) -> Vec<u8> {
+    let function = pdu[0];
+    match handle(handler, pdu) {
+        Ok(mut data) => {
+            data.insert(0, function);
+            data
+        },
+        Err(exception) => vec![function | 0x80, exception as u8]
+    }
+}
+
+fn quantity(count: usize, max: usize) -> ExceptionResult<usize> {
+    if count == 0 || count > max {
+        return Err(Exception::IllegalDataValue);
+    }
+    Ok(count)
+}
+
+fn handle<H: SlaveHandler>(handler: &mut H, pdu: &[u8]) -> ExceptionResult<Vec<u8>> {
+    // exception responses and unknown codes have no address to decode
+    let header = match pdu[0] {
+        READ_COILS | READ_DISCRETE_INPUTS | READ_HOLDING_REGISTERS | READ_INPUT_REGISTERS |
+        WRITE_SINGLE_COIL | WRITE_SINGLE_REGISTER => 5,
+        WRITE_MULTIPLE_COILS | WRITE_MULTIPLE_REGISTERS => 6,
+        _ => return Err(Exception::IllegalFunction)
+    };
+    if pdu.len() < header || (header == 6 && pdu.len() != header + pdu[5] as usize) {
+        return Err(Exception::IllegalDataValue);
+    }
+    let address = word(&pdu[1..]);
+    let value = word(&pdu[3..]);
+    let with_count = |data: Vec<u8>| {
+        let mut out = vec![data.len() as u8];
+        out.extend_from_slice(&data);
+        out
+    };
+    match pdu[0] {
+        READ_COILS => {
+            let count = try!(quantity(value as usize, MAX_READ_BITS));
+            Ok(with_count(pack_bits(&try!(handler.read_coils(address, count)))))
+        },
+        READ_DISCRETE_INPUTS => {
+            let count = try!(quantity(value as usize, MAX_READ_BITS));
+            Ok(with_count(pack_bits(&try!(handler.read_discrete_inputs(address, count)))))
+        },
+        READ_HOLDING_REGISTERS => {
+            let count = try!(quantity(value as usize, MAX_READ_REGISTERS));
+            Ok(with_count(pack_registers(&try!(handler.read_holding_registers(address, count)))))
+        },
+        READ_INPUT_REGISTERS => {
+            let count = try!(quantity(value as usize, MAX_READ_REGISTERS));
+            Ok(with_count(pack_registers(&try!(handler.read_input_registers(address, count)))))
+        },
+        WRITE_SINGLE_COIL => {
+            let on = match value {
+                0xFF00 => true,
+                0x0000 => false,
+                _ => return Err(Exception::IllegalDataValue)
+            };
+            try!(handler.write_coils(address, &[on]));
+            Ok(pdu[1..5].to_vec())
+        },
+        WRITE_SINGLE_REGISTER => {
+            try!(handler.write_registers(address, &[value]));
+            Ok(pdu[1..5].to_vec())
+        },
+        WRITE_MULTIPLE_COILS => {
+            let count = try!(quantity(value as usize, MAX_WRITE_BITS));
+            if pdu[5] as usize != (count + 7) / 8 {
+                return Err(Exception::IllegalDataValue);
+            }
+            try!(handler.write_coils(address, &unpack_bits(&pdu[6..], count)));
+            Ok(pdu[1..5].to_vec())
+        },
+        WRITE_MULTIPLE_REGISTERS => {
+            let count = try!(quantity(value as usize, MAX_WRITE_REGISTERS));
+            if pdu[5] as usize != count * 2 {
+                return Err(Exception::IllegalDataValue);
+            }
+            try!(handler.write_registers(address, &unpack_registers(&pdu[6..])));
+            Ok(pdu[1..5].to_vec())
+        },
+        _ => Err(Exception::IllegalFunction)
+    }
+}
+
+trait OutputPin: Send {
+    fn write(&mut self, value: bool) -> Result<()>;
+}
+
+impl<P: DigitalWrite + Send> OutputPin for P {
+    fn write(&mut self, value: bool) -> Result<()> {
+        self.digital_write(if value { Logic::High } else { Logic::Low })
+    }
+}
+
+trait InputPin: Send {
+    fn read(&mut self) -> Result<bool>;
+}
+
+impl<P: DigitalRead + Send> InputPin for P {
+    fn read(&mut self) -> Result<bool> {
+        self.is_high()
+    }
+}
+
+// In-memory tables, coils and discrete inputs can be backed by pins
+pub struct DataMap {
+    coils: Vec<bool>,
+    discrete_inputs: Vec<bool>,
+    holding_registers: Vec<u16>,
+    input_registers: Vec<u16>,
+    coil_pins: Vec<(u16, Box<OutputPin>)>,
+    input_pins: Vec<(u16, Box<InputPin>)>
+}
+
+fn range(address: u16, count: usize, len: usize) -> ExceptionResult<::std::ops::Range<usize>> {
+    let start = address as usize;
+    if start + count > len {
+        return Err(Exception::IllegalDataAddress);
+    }
+    Ok(start..start + count)
+}
+
+impl DataMap {
+    // Table sizes, addresses start at 0
+    pub fn new(coils: usize, discrete_inputs: usize, holding_registers: usize, input_registers: usize) -> DataMap {
+        DataMap {
+            coils: vec![false; coils],
+            discrete_inputs: vec![false; discrete_inputs],
+            holding_registers: vec![0; holding_registers],
+            input_registers: vec![0; input_registers],
+            coil_pins: Vec::new(),
+            input_pins: Vec::new()
+        }
+    }
+
+    // Writes of the coil go to the pin
+    pub fn coil_pin<P: DigitalWrite + Send + 'static>(&mut self, address: u16, pin: P) -> Result<&mut Self> {
+        if address as usize >= self.coils.len() {
+            return Err(Error::InvalidAddress);
+        }
+        self.coil_pins.push((address, Box::new(pin)));
+        Ok(self)
+    }
+
+    // The input is read from the pin on every request
+    pub fn input_pin<P: DigitalRead + Send + 'static>(&mut self, address: u16, pin: P) -> Result<&mut Self> {
+        if address as usize >= self.discrete_inputs.len() {
+            return Err(Error::InvalidAddress);
+        }
+        self.input_pins.push((address, Box::new(pin)));
+        Ok(self)
+    }
+
+    pub fn coils(&self) -> &[bool] {
+        &self.coils
+    }
+
+    pub fn discrete_inputs(&mut self) -> &mut [bool] {
+        &mut self.discrete_inputs
+    }
+
+    pub fn holding_registers(&mut self) -> &mut [u16] {
+        &mut self.holding_registers
+    }
+
+    pub fn input_registers(&mut self) -> &mut [u16] {
+        &mut self.input_registers
+    }
+}
+
+impl SlaveHandler for DataMap {
+    fn read_coils(&mut self, address: u16, count: usize) -> ExceptionResult<Vec<bool>> {
+        let range = try!(range(address, count, self.coils.len()));
+        Ok(self.coils[range].to_vec())
+    }
+
+    fn read_discrete_inputs(&mut self, address: u16, count: usize) -> ExceptionResult<Vec<bool>> {
+        let range = try!(range(address, count, self.discrete_inputs.len()));
+        for &mut (pin_address, ref mut pin) in self.input_pins.iter_mut() {
+            if range.start <= pin_address as usize && (pin_address as usize) < range.end {
+                let level = try!(pin.read().map_err(|_| Exception::ServerDeviceFailure));
+                self.discrete_inputs[pin_address as usize] = level;
+            }
+        }
+        Ok(self.discrete_inputs[range].to_vec())
+    }
+
+    fn read_holding_registers(&mut self, address: u16, count: usize) -> ExceptionResult<Vec<u16>> {
+        let range = try!(range(address, count, self.holding_registers.len()));
+        Ok(self.holding_registers[range].to_vec())
+    }
+
+    fn read_input_registers(&mut self, address: u16, count: usize) -> ExceptionResult<Vec<u16>> {
+        let range = try!(range(address, count, self.input_registers.len()));
+        Ok(self.input_registers[range].to_vec())
+    }
+
+    fn write_coils(&mut self, address: u16, values: &[bool]) -> ExceptionResult<()> {
+        let range = try!(range(address, values.len(), self.coils.len()));
+        for &mut (pin_address, ref mut pin) in self.coil_pins.iter_mut() {
+            if range.start <= pin_address as usize && (pin_address as usize) < range.end {
+                let value = values[pin_address as usize - range.start];
+                try!(pin.write(value).map_err(|_| Exception::ServerDeviceFailure));
+            }
+        }
+        self.coils[range].copy_from_slice(values);
+        Ok(())
+    }
+
+    fn write_registers(&mut self, address: u16, values: &[u16]) -> ExceptionResult<()> {
+        let range = try!(range(address, values.len(), self.holding_registers.len()));
+        self.holding_registers[range].copy_from_slice(values);
+        Ok(())
+    }
+}
+
+// Modbus RTU server for one unit address, broadcasts are executed silently
+pub struct RtuSlave<D> {
+    rtu: Rtu<D>,
+    unit: u8
+}
+
+impl<D: DigitalWrite> RtuSlave<D> {
+    pub fn new(rtu: Rtu<D>, unit: u8) -> RtuSlave<D> {
+        RtuSlave { rtu: rtu, unit: unit }
+    }
+
+    pub fn into_inner(self) -> Rtu<D> {
+        self.rtu
+    }
+
+    // Serves one request, false on timeout or a frame not for us (or broken)
+    pub fn process<H: SlaveHandler>(&mut self, handler: &mut H, timeout: Option<Duration>) -> Result<bool> {
+        let frame = match self.rtu.receive_frame(true, timeout) {
+            Ok(frame) => frame,
+            Err(ref err) if err.kind() == io::ErrorKind::TimedOut || err.kind() == io::ErrorKind::InvalidData => {
+                return Ok(false);
+            },
+            Err(err) => return Err(err.into())
+        };
+        if frame[0] != self.unit && frame[0] != 0 {
+            return Ok(false);
+        }
+
+        let pdu = respond(handler, &frame[1..]);
+        if frame[0] == 0 {
+            return Ok(true);
+        }
+        let mut response = Vec::with_capacity(pdu.len() + 1);
+        response.push(self.unit);
+        response.extend_from_slice(&pdu);
+        try!(self.rtu.send(&response));
+        Ok(true)
+    }
+
+    pub fn run<H: SlaveHandler>(&mut self, handler: &mut H) -> Result<()> {
+        loop {
+            try!(self.process(handler, None));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use std::io::prelude::*;
+    use serial::{Serial, SerialConfig};
+    use serial::pty::pty;
+    use {DigitalRead, DigitalWrite, DigitalLogic, Logic, Result, Error};
+    use super::super::{Rtu, RtuMaster, Exception, READ_COILS, WRITE_MULTIPLE_REGISTERS, crc16};
+    use super::{RtuSlave, DataMap, respond};
+
+    #[derive(Clone)]
+    struct Pin(Arc<Mutex<Vec<Logic>>>);
+
+    impl DigitalWrite for Pin {
+        fn digital_write<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+            self.0.lock().unwrap().push(level.logic_level());
+            Ok(())
+        }
+    }
+
+    impl DigitalRead for Pin {
+        fn digital_read(&mut self) -> Result<Logic> {
+            Ok(*self.0.lock().unwrap().last().unwrap_or(&Logic::Low))
+        }
+    }
+
+    #[test]
+    fn master_slave_over_pty() {
+        let (mut port, path) = pty();
+        let mut config = SerialConfig::new();
+        config.baud(115200);
+        let de = Pin(Arc::new(Mutex::new(Vec::new())));
+        let relay = Pin(Arc::new(Mutex::new(Vec::new())));
+        let button = Pin(Arc::new(Mutex::new(vec![Logic::High])));
+
+        let slave_port = Serial::open(&path, &config).unwrap();
+        let slave_de = de.clone();
+        let slave_relay = relay.clone();
+        let slave = thread::spawn(move || {
+            let mut map = DataMap::new(8, 8, 4, 2);
+            map.coil_pin(2, slave_relay).unwrap().input_pin(5, button).unwrap();
+            assert!(map.coil_pin(8, Pin(Arc::new(Mutex::new(Vec::new())))).is_err());
+            assert!(map.input_pin(8, Pin(Arc::new(Mutex::new(Vec::new())))).is_err());
+            map.holding_registers()[1] = 0x1234;
+            map.input_registers()[0] = 0xBEEF;
+            let mut slave = RtuSlave::new(Rtu::rs485(slave_port, slave_de).unwrap(), 0x11);
+            while slave.process(&mut map, Some(Duration::from_millis(500))).unwrap() {}
+            map
+        });
+
+        port.set_timeout(Some(Duration::from_secs(3)));
+        let mut master = RtuMaster::new(Rtu::new(port));
+        assert_eq!(master.read_holding_registers(0x11, 0, 2).unwrap(), vec![0, 0x1234]);
+        assert_eq!(master.read_input_registers(0x11, 0, 1).unwrap(), vec![0xBEEF]);
+        master.write_single_coil(0x11, 2, true).unwrap();
+        master.write_multiple_registers(0x11, 2, &[7, 8]).unwrap();
+        assert_eq!(master.read_coils(0x11, 0, 4).unwrap(), vec![false, false, true, false]);
+        assert_eq!(master.read_discrete_inputs(0x11, 4, 2).unwrap(), vec![false, true]);
+        match master.read_holding_registers(0x11, 3, 2) {
+            Err(Error::ModbusException(code)) => assert_eq!(code, Exception::IllegalDataAddress as u8),
+            other => panic!("{:?}", other)
+        }
+        // other unit, the slave stays silent and stops after its timeout
+        master.timeout(Duration::from_millis(50));
+        assert!(master.read_coils(0x12, 0, 1).is_err());
+        // the port gets its own timeout back, also after a failed read
+        assert_eq!(master.into_inner().serial().timeout(), Some(Duration::from_secs(3)));
+
+        let mut map = slave.join().unwrap();
+        assert_eq!(&map.holding_registers()[2..], &[7, 8]);
+        assert_eq!(*relay.0.lock().unwrap(), vec![Logic::High]);
+        // receive, then one send per response
+        let de = de.0.lock().unwrap();
+        assert_eq!(de.len(), 1 + 2 * 7);
+        assert_eq!(*de.last().unwrap(), Logic::Low);
+    }
+
+    #[test]
+    fn short_and_exception_pdus() {
+        let mut map = DataMap::new(8, 8, 4, 2);
+        assert_eq!(respond(&mut map, &[0x83, 0x02]), vec![0x83, Exception::IllegalFunction as u8]);
+        assert_eq!(respond(&mut map, &[READ_COILS, 0, 0]), vec![0x81, Exception::IllegalDataValue as u8]);
+        assert_eq!(respond(&mut map, &[WRITE_MULTIPLE_REGISTERS, 0, 0, 0, 1, 2, 0]),
+                   vec![0x90, Exception::IllegalDataValue as u8]);
+    }
+
+    fn frame(data: &[u8]) -> Vec<u8> {
+        let crc = crc16(data);
+        let mut frame = data.to_vec();
+        frame.push(crc as u8);
+        frame.push((crc >> 8) as u8);
+        frame
+    }
+
+    #[test]
+    fn exception_frame_to_slave() {
+        let (mut port, path) = pty();
+        port.set_timeout(Some(Duration::from_secs(1)));
+        let mut slave = RtuSlave::new(Rtu::new(Serial::open(&path, &SerialConfig::new()).unwrap()), 0x11);
+        let mut map = DataMap::new(8, 8, 4, 2);
+
+        port.write_all(&frame(&[0x11, 0x83, 0x02])).unwrap();
+        assert!(slave.process(&mut map, Some(Duration::from_secs(1))).unwrap());
+        let mut buf = [0u8; 5];
+        port.read_exact(&mut buf).unwrap();
+        assert_eq!(buf.to_vec(), frame(&[0x11, 0x83, Exception::IllegalFunction as u8]));
+
+        // broadcast is dropped silently and the slave keeps serving
+        port.write_all(&frame(&[0x00, 0x83, 0x02])).unwrap();
+        assert!(slave.process(&mut map, Some(Duration::from_secs(1))).unwrap());
+        port.write_all(&frame(&[0x11, READ_COILS, 0, 0, 0, 1])).unwrap();
+        assert!(slave.process(&mut map, Some(Duration::from_secs(1))).unwrap());
+        let mut buf = [0u8; 6];
+        port.read_exact(&mut buf).unwrap();
+        assert_eq!(buf.to_vec(), frame(&[0x11, READ_COILS, 1, 0]));
+    }
+}
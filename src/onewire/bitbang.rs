@@ -0,0 +1,384 @@
+use std::fmt;
+use std::io;
+use bcm270x::{PinOptions, PinIO};
+use {Result, Error, Logic, IoPin, delay_hard};
+use super::{crc8, temperature};
+
+const SEARCH_ROM: u8 = 0xF0;
+const READ_ROM: u8 = 0x33;
+const MATCH_ROM: u8 = 0x55;
+const SKIP_ROM: u8 = 0xCC;
+const CONVERT_T: u8 = 0x44;
+const READ_SCRATCHPAD: u8 = 0xBE;
+
+// Time slots of the bus, the master does the timing
+pub trait OneWireLine {
+    // true when a device answered with a presence pulse
+    fn reset(&mut self) -> Result<bool>;
+    fn write_bit(&mut self, bit: bool) -> Result<()>;
+    fn read_bit(&mut self) -> Result<bool>;
+}
+
+// Open drain on a native pin: output low pulls the line, input lets the
+// pull-up (4.7k) release it.
+// Standard speed slots, busy waited; a preempted slot shows up as a CRC error.
+pub struct GpioOneWire {
+    pin: PinIO
+}
+
+impl GpioOneWire {
+    pub fn new(options: &PinOptions) -> GpioOneWire {
+        GpioOneWire { pin: options.io() }
+    }
+
+    // register writes on the native pin never fail
+    #[inline(always)]
+    fn pull_low(&mut self) {
+        let _ = self.pin.set_output(Logic::Low);
+    }
+
+    #[inline(always)]
+    fn release(&mut self) {
+        let _ = self.pin.set_input();
+    }
+
+    #[inline(always)]
+    fn sample(&mut self) -> bool {
+        self.pin.read().ok() == Some(Logic::High)
+    }
+}
+
+impl OneWireLine for GpioOneWire {
+    fn reset(&mut self) -> Result<bool> {
+        self.release();
+        if !self.sample() {
+            return Err(io::Error::new(io::ErrorKind::Other, "1-Wire bus held low").into());
+        }
+        self.pull_low();
+        delay_hard(480);
+        self.release();
+        delay_hard(70);
+        let presence = !self.sample();
+        delay_hard(410);
+        Ok(presence)
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<()> {
+        self.pull_low();
+        if bit {
+            delay_hard(6);
+            self.release();
+            delay_hard(64);
+        } else {
+            delay_hard(60);
+            self.release();
+            delay_hard(10);
+        }
+        Ok(())
+    }
+
+    fn read_bit(&mut self) -> Result<bool> {
+        self.pull_low();
+        delay_hard(6);
+        self.release();
+        delay_hard(9);
+        let bit = self.sample();
+        delay_hard(55);
+        Ok(bit)
+    }
+}
+
+// 64-bit ROM code, family code first, CRC last
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rom(pub [u8; 8]);
+
+impl Rom {
+    pub fn family(&self) -> u8 {
+        self.0[0]
+    }
+
+    pub fn is_valid(&self) -> bool {
+        crc8(&self.0[..7]) == self.0[7]
+    }
+}
+
+// Same id as the w1 subsystem, e.g. 28-0316a2795cff
+impl fmt::Display for Rom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{:02x}-", self.0[0]));
+        for byte in self.0[1..7].iter().rev() {
+            try!(write!(f, "{:02x}", byte));
+        }
+        Ok(())
+    }
+}
+
+// 1-Wire master in userspace, e.g. OneWireMaster::new(GpioOneWire::new(&pin))
+pub struct OneWireMaster<L> {
+    line: L
+}
+
+impl<L: OneWireLine> OneWireMaster<L> {
+    pub fn new(line: L) -> OneWireMaster<L> {
+        OneWireMaster { line: line }
+    }
+
+    pub fn into_inner(self) -> L {
+        self.line
+    }
+
+    pub fn reset(&mut self) -> Result<bool> {
+        self.line.reset()
+    }
+
+    // LSB first
+    pub fn write_byte(&mut self, byte: u8) -> Result<()> {
+        for n in 0..8 {
+            try!(self.line.write_bit(byte & (1 << n) != 0));
+        }
+        Ok(())
+    }
+
+    pub fn read_byte(&mut self) -> Result<u8> {
+        let mut byte = 0u8;
+        for n in 0..8 {
+            if try!(self.line.read_bit()) {
+                byte |= 1 << n;
+            }
+        }
+        Ok(byte)
+    }
+
+    pub fn write_bytes(&mut self, data: &[u8]) -> Result<()> {
+        for &byte in data {
+            try!(self.write_byte(byte));
+        }
+        Ok(())
+    }
+
+    pub fn read_bytes(&mut self, buf: &mut [u8]) -> Result<()> {
+        for byte in buf.iter_mut() {
+            *byte = try!(self.read_byte());
+        }
+        Ok(())
+    }
+
+    // Reset and address one device, None addresses all (SKIP ROM)
+    pub fn select(&mut self, rom: Option<&Rom>) -> Result<()> {
+        if !try!(self.reset()) {
+            return Err(Error::DeviceNotResponding);
+        }
+        match rom {
+            Some(rom) => {
+                try!(self.write_byte(MATCH_ROM));
+                self.write_bytes(&rom.0)
+            },
+            None => self.write_byte(SKIP_ROM)
+        }
+    }
+
+    // Only with a single device on the bus
+    pub fn read_rom(&mut self) -> Result<Rom> {
+        if !try!(self.reset()) {
+            return Err(Error::DeviceNotResponding);
+        }
+        try!(self.write_byte(READ_ROM));
+        let mut rom = Rom([0; 8]);
+        try!(self.read_bytes(&mut rom.0));
+        if !rom.is_valid() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "ROM CRC mismatch").into());
+        }
+        Ok(rom)
+    }
+
+    // ROM search of Maxim AN187, all devices in ascending bit order
+    pub fn search(&mut self) -> Result<Vec<Rom>> {
+        let mut roms = Vec::new();
+        let mut rom = [0u8; 8];
+        let mut last_discrepancy = 0;
+        loop {
+            if !try!(self.reset()) {
+                return Ok(roms);
+            }
+            try!(self.write_byte(SEARCH_ROM));
+
+            let mut last_zero = 0;
+            for bit in 1..65 {
+                let (byte, mask) = ((bit - 1) / 8, 1u8 << ((bit - 1) % 8));
+                let id_bit = try!(self.line.read_bit());
+                let complement = try!(self.line.read_bit());
+                let direction = match (id_bit, complement) {
+                    // nobody left, a device dropped off mid search
+                    (true, true) => return Err(io::Error::new(io::ErrorKind::InvalidData, "1-Wire search lost all devices").into()),
+                    (id_bit, complement) if id_bit != complement => id_bit,
+                    // discrepancy, devices with both values are present
+                    _ => {
+                        let direction = if bit < last_discrepancy {
+                            rom[byte] & mask != 0
+                        } else {
+                            bit == last_discrepancy
+                        };
+                        if !direction {
+                            last_zero = bit;
+                        }
+                        direction
+                    }
+                };
+                if direction {
+                    rom[byte] |= mask;
+                } else {
+                    rom[byte] &= !mask;
+                }
+                try!(self.line.write_bit(direction));
+            }
+
+            let found = Rom(rom);
+            if !found.is_valid() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "ROM CRC mismatch").into());
+            }
+            roms.push(found);
+            last_discrepancy = last_zero;
+            if last_discrepancy == 0 {
+                return Ok(roms);
+            }
+        }
+    }
+
+    // Starts a conversion on all thermometers, wait 750 ms at 12 bits.
+    // The bus must not be used while parasite powered devices convert.
+    pub fn convert_all(&mut self) -> Result<()> {
+        try!(self.select(None));
+        self.write_byte(CONVERT_T)
+    }
+
+    pub fn read_scratchpad(&mut self, rom: &Rom) -> Result<[u8; 9]> {
+        try!(self.select(Some(rom)));
+        try!(self.write_byte(READ_SCRATCHPAD));
+        let mut scratchpad = [0u8; 9];
+        try!(self.read_bytes(&mut scratchpad));
+        Ok(scratchpad)
+    }
+
+    // Celsius of the last conversion, the CRC is checked
+    pub fn read_temperature(&mut self, rom: &Rom) -> Result<f32> {
+        temperature(rom.family(), &try!(self.read_scratchpad(rom)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use Result;
+    use super::super::crc8;
+    use super::{OneWireLine, OneWireMaster, Rom};
+
+    enum State {
+        Command(u8, usize),
+        Search(usize, bool),
+        Match(usize),
+        Function(u8, usize),
+        Read(Vec<bool>)
+    }
+
+    // Devices answering on the bit level, open drain wired-and
+    struct Bus {
+        devices: Vec<(Rom, [u8; 9])>,
+        active: Vec<bool>,
+        state: State
+    }
+
+    fn bit(rom: &Rom, n: usize) -> bool {
+        rom.0[n / 8] & (1 << (n % 8)) != 0
+    }
+
+    impl OneWireLine for Bus {
+        fn reset(&mut self) -> Result<bool> {
+            self.active = vec![true; self.devices.len()];
+            self.state = State::Command(0, 0);
+            Ok(!self.devices.is_empty())
+        }
+
+        fn write_bit(&mut self, value: bool) -> Result<()> {
+            let next = match self.state {
+                State::Command(byte, n) | State::Function(byte, n) => {
+                    let byte = byte | (value as u8) << n;
+                    let function = match self.state { State::Function(..) => true, _ => false };
+                    if n < 7 {
+                        if function { State::Function(byte, n + 1) } else { State::Command(byte, n + 1) }
+                    } else if function {
+                        assert_eq!(byte, 0xBE);
+                        let selected = self.active.iter().position(|&a| a).unwrap();
+                        let scratchpad = self.devices[selected].1;
+                        let mut bits: Vec<bool> = (0..72).map(|n| scratchpad[n / 8] & (1 << (n % 8)) != 0).collect();
+                        bits.reverse();
+                        State::Read(bits)
+                    } else {
+                        match byte {
+                            0xF0 => State::Search(0, false),
+                            0x55 => State::Match(0),
+                            _ => panic!("command {:02x}", byte)
+                        }
+                    }
+                },
+                State::Search(n, _) => {
+                    for (i, &(ref rom, _)) in self.devices.iter().enumerate() {
+                        self.active[i] = self.active[i] && bit(rom, n) == value;
+                    }
+                    State::Search(n + 1, false)
+                },
+                State::Match(n) => {
+                    for (i, &(ref rom, _)) in self.devices.iter().enumerate() {
+                        self.active[i] = self.active[i] && bit(rom, n) == value;
+                    }
+                    if n == 63 { State::Function(0, 0) } else { State::Match(n + 1) }
+                },
+                State::Read(_) => panic!("write while reading")
+            };
+            self.state = next;
+            Ok(())
+        }
+
+        fn read_bit(&mut self) -> Result<bool> {
+            let devices = &self.devices;
+            let active = &self.active;
+            let wired_and = |f: &Fn(&Rom) -> bool| {
+                devices.iter().zip(active.iter()).filter(|&(_, &a)| a).all(|(&(ref rom, _), _)| f(rom))
+            };
+            match self.state {
+                State::Search(n, false) => {
+                    self.state = State::Search(n, true);
+                    Ok(wired_and(&|rom| bit(rom, n)))
+                },
+                State::Search(n, true) => Ok(wired_and(&|rom| !bit(rom, n))),
+                State::Read(ref mut bits) => Ok(bits.pop().unwrap_or(true)),
+                _ => Ok(true)
+            }
+        }
+    }
+
+    fn rom(family: u8, serial: u8) -> Rom {
+        let mut rom = [family, serial, 0x5C, 0x79, 0xA2, 0x16, 0x03, 0];
+        rom[7] = crc8(&rom[..7]);
+        Rom(rom)
+    }
+
+    #[test]
+    fn search_and_read() {
+        let scratchpad = [0x72, 0x01, 0x4B, 0x46, 0x7F, 0xFF, 0x0E, 0x10, 0x57];
+        let devices = vec![rom(0x28, 0xFF), rom(0x28, 0x01), rom(0x10, 0x80), rom(0x28, 0x03)];
+        let mut master = OneWireMaster::new(Bus {
+            devices: devices.iter().map(|&rom| (rom, scratchpad)).collect(),
+            active: Vec::new(),
+            state: State::Command(0, 0)
+        });
+
+        let mut found = master.search().unwrap();
+        assert_eq!(found.len(), 4);
+        found.sort();
+        let mut expected = devices.clone();
+        expected.sort();
+        assert_eq!(found, expected);
+        assert_eq!(devices[0].to_string(), "28-0316a2795cff");
+
+        assert_eq!(master.read_temperature(&devices[3]).unwrap(), 23.125);
+    }
+}
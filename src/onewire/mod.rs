@@ -0,0 +1,86 @@
+use std::io;
+use {Result, Error};
+
+mod w1;
+mod bitbang;
+
+pub use self::w1::{OneWire, Device, Master, Thermometer};
+pub use self::bitbang::{OneWireLine, OneWireMaster, GpioOneWire, Rom};
+
+pub const FAMILY_DS18S20: u8 = 0x10;
+pub const FAMILY_DS1822: u8 = 0x22;
+pub const FAMILY_DS18B20: u8 = 0x28;
+pub const FAMILY_DS1825: u8 = 0x3B;
+
+pub fn is_thermometer(family: u8) -> bool {
+    match family {
+        FAMILY_DS18S20 | FAMILY_DS1822 | FAMILY_DS18B20 | FAMILY_DS1825 => true,
+        _ => false
+    }
+}
+
+// Dallas/Maxim CRC-8, over ROM codes and scratchpads
+pub fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in data {
+        let mut byte = byte;
+        for _ in 0..8 {
+            let mix = (crc ^ byte) & 1;
+            crc >>= 1;
+            if mix != 0 {
+                crc ^= 0x8C;
+            }
+            byte >>= 1;
+        }
+    }
+    crc
+}
+
+// Celsius from a 9 byte scratchpad, the CRC is checked
+pub fn temperature(family: u8, scratchpad: &[u8; 9]) -> Result<f32> {
+    if scratchpad.iter().all(|&b| b == 0) {
+        // pulled low, no sensor answered
+        return Err(Error::DeviceNotResponding);
+    }
+    if crc8(&scratchpad[..8]) != scratchpad[8] {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "scratchpad CRC mismatch").into());
+    }
+    let raw = ((scratchpad[1] as u16) << 8 | scratchpad[0] as u16) as i16;
+    match family {
+        // 0.5 degree steps, COUNT_REMAIN and COUNT_PER_C give the fraction
+        FAMILY_DS18S20 => {
+            let count_remain = scratchpad[6] as f32;
+            let count_per_c = scratchpad[7] as f32;
+            let whole = (raw >> 1) as f32;
+            if count_per_c == 0.0 {
+                Ok(raw as f32 / 2.0)
+            } else {
+                Ok(whole - 0.25 + (count_per_c - count_remain) / count_per_c)
+            }
+        },
+        FAMILY_DS1822 | FAMILY_DS18B20 | FAMILY_DS1825 => Ok(raw as f32 / 16.0),
+        _ => Err(Error::UnsupportedHardware)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crc8, temperature, FAMILY_DS18B20, FAMILY_DS18S20};
+
+    #[test]
+    fn scratchpads() {
+        // ROM code example of Maxim AN27
+        assert_eq!(crc8(&[0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00]), 0xA2);
+        let b20 = [0x72, 0x01, 0x4B, 0x46, 0x7F, 0xFF, 0x0E, 0x10, 0x57];
+        assert_eq!(temperature(FAMILY_DS18B20, &b20).unwrap(), 23.125);
+        let negative = [0x5E, 0xFF, 0x4B, 0x46, 0x7F, 0xFF, 0x02, 0x10, crc8(&[0x5E, 0xFF, 0x4B, 0x46, 0x7F, 0xFF, 0x02, 0x10])];
+        assert_eq!(temperature(FAMILY_DS18B20, &negative).unwrap(), -10.125);
+        let s20 = [0x32, 0x00, 0x4B, 0x46, 0xFF, 0xFF, 0x0C, 0x10, crc8(&[0x32, 0x00, 0x4B, 0x46, 0xFF, 0xFF, 0x0C, 0x10])];
+        assert_eq!(temperature(FAMILY_DS18S20, &s20).unwrap(), 25.0);
+
+        let mut broken = b20;
+        broken[0] ^= 1;
+        assert!(temperature(FAMILY_DS18B20, &broken).is_err());
+        assert!(temperature(FAMILY_DS18B20, &[0; 9]).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use std::io::prelude::*;
+use std::io;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use {Result, Error, delay_ms};
+use super::{is_thermometer, temperature};
+
+fn read_string(path: &Path) -> Result<String> {
+    let mut s = String::new();
+    try!(try!(File::open(path)).read_to_string(&mut s));
+    Ok(s)
+}
+
+fn write_string(path: &Path, value: &str) -> Result<()> {
+    let mut f = try!(OpenOptions::new().write(true).open(path));
+    try!(f.write_all(value.as_bytes()));
+    Ok(())
+}
+
+// w1 subsystem (w1-gpio overlay, DS2482 bridges), one directory per
+// slave named FF-SSSSSSSSSSSS plus one w1_bus_masterN per bus
+#[derive(Clone, Debug)]
+pub struct OneWire {
+    root: PathBuf
+}
+
+impl OneWire {
+    pub fn new() -> OneWire {
+        OneWire::with_root("/sys/bus/w1/devices")
+    }
+
+    // Another devices directory, e.g. a fixture tree
+    pub fn with_root<P: AsRef<Path>>(root: P) -> OneWire {
+        OneWire { root: root.as_ref().to_path_buf() }
+    }
+
+    fn entries(&self) -> Result<Vec<(String, PathBuf)>> {
+        let mut entries = Vec::new();
+        for entry in try!(fs::read_dir(&self.root)) {
+            let entry = try!(entry);
+            entries.push((entry.file_name().to_string_lossy().into_owned(), entry.path()));
+        }
+        entries.sort();
+        Ok(entries)
+    }
+
+    pub fn devices(&self) -> Result<Vec<Device>> {
+        let mut devices = Vec::new();
+        for (name, path) in try!(self.entries()) {
+            if let Some(device) = Device::parse(&name, path) {
+                devices.push(device);
+            }
+        }
+        Ok(devices)
+    }
+
+    pub fn masters(&self) -> Result<Vec<Master>> {
+        Ok(try!(self.entries()).into_iter()
+            .filter(|&(ref name, _)| name.starts_with("w1_bus_master"))
+            .map(|(name, path)| Master { name: name, path: path })
+            .collect())
+    }
+
+    pub fn thermometers(&self) -> Result<Vec<Thermometer>> {
+        Ok(try!(self.devices()).into_iter()
+            .filter(|device| is_thermometer(device.family))
+            .map(|device| Thermometer { device: device })
+            .collect())
+    }
+
+    pub fn thermometer(&self, id: &str) -> Result<Thermometer> {
+        match Device::parse(id, self.root.join(id)) {
+            Some(ref device) if is_thermometer(device.family) && device.path.exists() => {
+                Ok(Thermometer { device: device.clone() })
+            },
+            _ => Err(Error::DeviceNotResponding)
+        }
+    }
+
+    // Starts a conversion on every thermometer of every bus at once
+    pub fn convert_all(&self) -> Result<()> {
+        for master in try!(self.masters()) {
+            try!(master.convert());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Device {
+    id: String,
+    family: u8,
+    path: PathBuf
+}
+
+impl Device {
+    fn parse(name: &str, path: PathBuf) -> Option<Device> {
+        let mut parts = name.splitn(2, '-');
+        let family = parts.next().and_then(|family| u8::from_str_radix(family, 16).ok());
+        let serial = parts.next().map(|serial| serial.len() == 12 && u64::from_str_radix(serial, 16).is_ok());
+        match (family, serial) {
+            (Some(family), Some(true)) => Some(Device { id: name.to_string(), family: family, path: path }),
+            _ => None
+        }
+    }
+
+    // e.g. 28-0316a2795cff
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn family(&self) -> u8 {
+        self.family
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Master {
+    name: String,
+    path: PathBuf
+}
+
+impl Master {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Ids the master found on its last search
+    pub fn slaves(&self) -> Result<Vec<String>> {
+        let list = try!(read_string(&self.path.join("w1_master_slaves")));
+        Ok(list.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && *line != "not found.")
+            .map(|line| line.to_string())
+            .collect())
+    }
+
+    // Bulk conversion (w1_therm therm_bulk_read), read the thermometers when done
+    pub fn convert(&self) -> Result<()> {
+        write_string(&self.path.join("therm_bulk_read"), "trigger\n")
+    }
+
+    // -1 while converting, 1 when results wait, 0 with nothing pending
+    pub fn converting(&self) -> Result<bool> {
+        let state = try!(read_string(&self.path.join("therm_bulk_read")));
+        Ok(try!(state.trim().parse::<i32>()) < 0)
+    }
+
+    // Waits for a bulk conversion, 750 ms at 12 bits
+    pub fn wait_converted(&self, timeout: Duration) -> Result<()> {
+        let mut waited = 0;
+        while try!(self.converting()) {
+            if waited >= timeout.as_millis() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "conversion still running").into());
+            }
+            delay_ms(10);
+            waited += 10;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Thermometer {
+    device: Device
+}
+
+impl Thermometer {
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    // From the w1_slave dump, which converts unless a bulk read result waits:
+    // "72 01 4b 46 7f ff 0e 10 57 : crc=57 YES"
+    pub fn scratchpad(&self) -> Result<[u8; 9]> {
+        let dump = try!(read_string(&self.device.path.join("w1_slave")));
+        let line = dump.lines().next().unwrap_or("");
+        let mut parts = line.splitn(2, ':');
+        let bytes = parts.next().unwrap_or("");
+        if !parts.next().map(|crc| crc.trim().ends_with("YES")).unwrap_or(false) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "w1 CRC check failed").into());
+        }
+
+        let mut scratchpad = [0u8; 9];
+        let mut count = 0;
+        for hex in bytes.split_whitespace() {
+            if count == 9 {
+                break;
+            }
+            scratchpad[count] = try!(u8::from_str_radix(hex, 16));
+            count += 1;
+        }
+        if count != 9 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "short w1_slave dump").into());
+        }
+        Ok(scratchpad)
+    }
+
+    // Celsius, the scratchpad CRC is checked again here
+    pub fn read_celsius(&self) -> Result<f32> {
+        temperature(self.device.family, &try!(self.scratchpad()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::prelude::*;
+    use std::path::{Path, PathBuf};
+    use super::OneWire;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path).unwrap().write_all(content.as_bytes()).unwrap();
+    }
+
+    fn fixture(name: &str) -> PathBuf {
+        let root = env::temp_dir().join(format!("cupi-w1-{}-{}", name, ::std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        write(&root.join("w1_bus_master1/w1_master_slaves"), "28-0316a2795cff\n10-000802319e4e\n81-0000001c5d0a\n");
+        write(&root.join("w1_bus_master1/therm_bulk_read"), "0\n");
+        write(&root.join("28-0316a2795cff/w1_slave"),
+              "72 01 4b 46 7f ff 0e 10 57 : crc=57 YES\n72 01 4b 46 7f ff 0e 10 57 t=23125\n");
+        write(&root.join("10-000802319e4e/w1_slave"),
+              "32 00 4b 46 ff ff 0c 10 1c : crc=1c NO\n32 00 4b 46 ff ff 0c 10 1c t=25000\n");
+        fs::create_dir_all(root.join("81-0000001c5d0a")).unwrap();
+        root
+    }
+
+    #[test]
+    fn fixture_tree() {
+        let root = fixture("tree");
+        let w1 = OneWire::with_root(&root);
+
+        let ids: Vec<String> = w1.devices().unwrap().iter().map(|d| d.id().to_string()).collect();
+        assert_eq!(ids, vec!["10-000802319e4e", "28-0316a2795cff", "81-0000001c5d0a"]);
+        let thermometers = w1.thermometers().unwrap();
+        assert_eq!(thermometers.len(), 2);
+
+        assert_eq!(w1.thermometer("28-0316a2795cff").unwrap().read_celsius().unwrap(), 23.125);
+        // the kernel saw a bad CRC
+        assert!(thermometers[0].read_celsius().is_err());
+
+        let master = &w1.masters().unwrap()[0];
+        assert_eq!(master.slaves().unwrap().len(), 3);
+        w1.convert_all().unwrap();
+        let mut trigger = String::new();
+        File::open(root.join("w1_bus_master1/therm_bulk_read")).unwrap().read_to_string(&mut trigger).unwrap();
+        assert_eq!(trigger, "trigger\n");
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
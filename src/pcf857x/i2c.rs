@@ -0,0 +1,76 @@
+use std::io;
+use i2c::{I2CDevice, LinuxI2CDevice};
+use {Result, Error};
+use super::{Transport, SharedBus, Port, InterruptHandler, init};
+
+pub struct I2CTransport {
+    dev: Box<I2CDevice>
+}
+
+impl I2CTransport {
+    pub fn new<D: I2CDevice + 'static>(dev: D) -> I2CTransport {
+        I2CTransport { dev: Box::new(dev) }
+    }
+
+    pub fn open(bus: usize, address: u16) -> Result<I2CTransport> {
+        Ok(I2CTransport::new(try!(LinuxI2CDevice::open(bus, address))))
+    }
+}
+
+impl Transport for I2CTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.dev.read(buf)
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.dev.write(data)
+    }
+}
+
+// 8-bit PCF8574 (0x20-0x27) or PCF8574A (0x38-0x3F) on /dev/i2c-N
+pub struct PCF8574 {
+    bus: SharedBus
+}
+
+impl PCF8574 {
+    pub fn new(bus: usize, address: u16) -> Result<Self> {
+        match address {
+            0x20..=0x27 | 0x38..=0x3F => (),
+            _ => return Err(Error::InvalidAddress)
+        }
+        let transport = try!(I2CTransport::open(bus, address));
+        Ok(PCF8574 { bus: try!(init(Box::new(transport), 8)) })
+    }
+
+    pub fn port(&self) -> Port {
+        Port::new(self.bus.clone())
+    }
+
+    pub fn interrupt_handler(&self) -> InterruptHandler {
+        InterruptHandler::new(self.bus.clone())
+    }
+}
+
+// 16-bit PCF8575 (0x20-0x27) on /dev/i2c-N, P00-P07 are pins 0-7
+// and P10-P17 pins 8-15
+pub struct PCF8575 {
+    bus: SharedBus
+}
+
+impl PCF8575 {
+    pub fn new(bus: usize, address: u16) -> Result<Self> {
+        if address < 0x20 || address > 0x27 {
+            return Err(Error::InvalidAddress);
+        }
+        let transport = try!(I2CTransport::open(bus, address));
+        Ok(PCF8575 { bus: try!(init(Box::new(transport), 16)) })
+    }
+
+    pub fn port(&self) -> Port {
+        Port::new(self.bus.clone())
+    }
+
+    pub fn interrupt_handler(&self) -> InterruptHandler {
+        InterruptHandler::new(self.bus.clone())
+    }
+}
@@ -0,0 +1,139 @@
+use std::time::Duration;
+use mio::{Poll, Token};
+use sys::{self, Edge, EventDispatcher, EventId};
+use {Result, Logic, monotonic};
+use super::{SharedBus, lock};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PinEvent {
+    pub pin: usize,
+    pub level: Logic,
+    pub timestamp: Duration
+}
+
+// The chip has no interrupt flags, INT only says some input changed.
+// Changes are found by comparing with the levels of the previous read,
+// pins driven low by the latch are not reported.
+#[derive(Clone)]
+pub struct InterruptHandler {
+    bus: SharedBus,
+    last: u16
+}
+
+impl InterruptHandler {
+    pub(crate) fn new(bus: SharedBus) -> InterruptHandler {
+        // idle inputs sit at the pull-up level
+        let last = lock(&bus).latch();
+        InterruptHandler { bus: bus, last: last }
+    }
+
+    // Reads the port (releasing INT) and reports changed inputs
+    pub fn handle(&mut self) -> Result<Vec<PinEvent>> {
+        let timestamp = monotonic();
+        self.handle_at(timestamp)
+    }
+
+    fn handle_at(&mut self, timestamp: Duration) -> Result<Vec<PinEvent>> {
+        let mut bus = lock(&self.bus);
+        let levels = try!(bus.read());
+        let changed = (levels ^ self.last) & bus.latch();
+        self.last = levels;
+
+        Ok((0..bus.pins()).filter(|pin| changed & (1 << pin) != 0).map(|pin| PinEvent {
+            pin: pin,
+            level: if levels & (1 << pin) != 0 { Logic::High } else { Logic::Low },
+            timestamp: timestamp
+        }).collect())
+    }
+}
+
+// Interrupt handler attached to the sysfs pin wired to the chip INT output
+// (active low, open-drain)
+pub struct Interrupts {
+    handler: InterruptHandler,
+    host: sys::PinInput
+}
+
+impl Interrupts {
+    pub fn new(handler: InterruptHandler, host: sys::PinInput) -> Interrupts {
+        Interrupts { handler: handler, host: host }
+    }
+
+    // Blocks until some input changes, empty result means timeout
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Result<Vec<PinEvent>> {
+        try!(self.host.arm(Edge::FallingEdge));
+        // INT may already be asserted, then no falling edge will come
+        let pending = try!(self.handler.handle());
+        if !pending.is_empty() {
+            return Ok(pending);
+        }
+        match try!(self.host.next_event(timeout)) {
+            Some(event) => self.handler.handle_at(event.timestamp),
+            None => Ok(Vec::new())
+        }
+    }
+
+    pub fn trigger(&mut self, poll: &mut Poll, token: Token) -> Result<()> {
+        try!(self.host.trigger(poll, token, Edge::FallingEdge));
+        // release INT so the next change makes an edge
+        let _ = try!(self.handler.handle());
+        Ok(())
+    }
+
+    pub fn stop_trigger(&mut self, poll: &mut Poll) -> Result<()> {
+        self.host.stop_trigger(poll)
+    }
+
+    // Call on mio readiness of the trigger token
+    pub fn handle(&mut self) -> Result<Vec<PinEvent>> {
+        let event = try!(self.host.event());
+        self.handler.handle_at(event.timestamp)
+    }
+
+    // Calls back for every input change from the dispatcher thread
+    pub fn dispatch<F>(self, dispatcher: &mut EventDispatcher, mut callback: F) -> Result<EventId>
+        where F: FnMut(PinEvent) + Send + 'static
+    {
+        let mut handler = self.handler;
+        let _ = try!(handler.handle());
+        dispatcher.add(self.host, Edge::FallingEdge, None, move |event| {
+            if let Ok(events) = handler.handle_at(event.timestamp) {
+                for e in events {
+                    callback(e);
+                }
+            }
+        })
+    }
+
+    pub fn into_inner(self) -> (InterruptHandler, sys::PinInput) {
+        (self.handler, self.host)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Logic, DigitalWrite};
+    use super::super::{PCF857X, Simulator};
+
+    #[test]
+    fn changed_inputs() {
+        let sim = Simulator::new(8);
+        let chip = PCF857X::pcf8574(sim.clone()).unwrap();
+        let mut led = chip.port().output(7).unwrap();
+        led.low().unwrap();
+        let mut handler = chip.interrupt_handler();
+
+        sim.set_inputs(0xFA);
+        assert!(sim.interrupt());
+        let events: Vec<(usize, Logic)> = handler.handle().unwrap()
+            .into_iter().map(|e| (e.pin, e.level)).collect();
+        assert_eq!(events, vec![(0, Logic::Low), (2, Logic::Low)]);
+        assert!(!sim.interrupt());
+
+        sim.set_inputs(0xFE);
+        let events: Vec<(usize, Logic)> = handler.handle().unwrap()
+            .into_iter().map(|e| (e.pin, e.level)).collect();
+        assert_eq!(events, vec![(2, Logic::High)]);
+        assert!(handler.handle().unwrap().is_empty());
+    }
+}
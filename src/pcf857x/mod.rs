@@ -0,0 +1,119 @@
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use {Result, Error};
+
+mod port;
+mod interrupt;
+mod simulator;
+mod i2c;
+
+pub use self::port::{
+    Port,
+    PinInput,
+    PinOutput,
+    GroupInput,
+    GroupOutput
+};
+
+pub use self::interrupt::{
+    PinEvent,
+    InterruptHandler,
+    Interrupts
+};
+
+pub use self::simulator::Simulator;
+
+pub use self::i2c::{PCF8574, PCF8575, I2CTransport};
+
+// The chips have no registers, every transfer is the whole port
+// (P0 first, then P1 on PCF8575)
+pub trait Transport: Send {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<()>;
+    fn write(&mut self, data: &[u8]) -> io::Result<()>;
+}
+
+// Shadow of the output latch. Pins are quasi-bidirectional: a latch bit
+// written high is a weak pull-up and the pin can be read as input.
+pub(crate) struct Device {
+    transport: Box<Transport>,
+    pins: usize,
+    latch: u16
+}
+
+impl Device {
+    fn new(transport: Box<Transport>, pins: usize) -> Device {
+        // power-on: all pins high
+        Device { transport: transport, pins: pins, latch: 0xFFFF }
+    }
+
+    pub fn pins(&self) -> usize {
+        self.pins
+    }
+
+    pub fn mask(&self) -> u16 {
+        if self.pins == 16 { 0xFFFF } else { 0x00FF }
+    }
+
+    pub fn latch(&self) -> u16 {
+        self.latch & self.mask()
+    }
+
+    pub fn write(&mut self, latch: u16) -> io::Result<()> {
+        let buf = [latch as u8, (latch >> 8) as u8];
+        try!(self.transport.write(&buf[..self.pins / 8]));
+        self.latch = latch;
+        Ok(())
+    }
+
+    pub fn modify(&mut self, set: u16, clear: u16) -> io::Result<()> {
+        let latch = (self.latch | set) & !clear;
+        self.write(latch)
+    }
+
+    pub fn read(&mut self) -> io::Result<u16> {
+        let mut buf = [0u8; 2];
+        try!(self.transport.read(&mut buf[..self.pins / 8]));
+        Ok((buf[0] as u16 | (buf[1] as u16) << 8) & self.mask())
+    }
+}
+
+pub(crate) type SharedBus = Arc<Mutex<Device>>;
+
+pub(crate) fn lock(bus: &SharedBus) -> MutexGuard<Device> {
+    match bus.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+fn init(transport: Box<Transport>, pins: usize) -> Result<SharedBus> {
+    let mut dev = Device::new(transport, pins);
+    // all pins released, a missing chip does not ack
+    if dev.write(0xFFFF).is_err() {
+        return Err(Error::DeviceNotResponding);
+    }
+    Ok(Arc::new(Mutex::new(dev)))
+}
+
+// PCF8574/PCF8575 on any transport, e.g. Simulator in tests
+pub struct PCF857X {
+    bus: SharedBus
+}
+
+impl PCF857X {
+    pub fn pcf8574<T: Transport + 'static>(transport: T) -> Result<PCF857X> {
+        Ok(PCF857X { bus: try!(init(Box::new(transport), 8)) })
+    }
+
+    pub fn pcf8575<T: Transport + 'static>(transport: T) -> Result<PCF857X> {
+        Ok(PCF857X { bus: try!(init(Box::new(transport), 16)) })
+    }
+
+    pub fn port(&self) -> Port {
+        Port::new(self.bus.clone())
+    }
+
+    pub fn interrupt_handler(&self) -> InterruptHandler {
+        InterruptHandler::new(self.bus.clone())
+    }
+}
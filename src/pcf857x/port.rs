@@ -0,0 +1,197 @@
+use {Result, Error, Logic, DigitalLogic, DigitalWrite, DigitalRead};
+use super::{SharedBus, lock};
+
+// All pins of the chip. There is no direction register: inputs are
+// pins with the latch high, outputs drive low and pull up weakly.
+pub struct Port {
+    bus: SharedBus
+}
+
+impl Port {
+    pub(crate) fn new(bus: SharedBus) -> Port {
+        Port { bus: bus }
+    }
+
+    pub fn input(&mut self, pin: usize) -> Result<PinInput> {
+        let mut bus = lock(&self.bus);
+        if pin >= bus.pins() {
+            return Err(Error::InvalidAddress);
+        }
+
+        // release pin
+        try!(bus.modify(1 << pin, 0));
+
+        Ok(PinInput {
+            bus: self.bus.clone(),
+            pin: pin
+        })
+    }
+
+    pub fn output(&mut self, pin: usize) -> Result<PinOutput> {
+        if pin >= lock(&self.bus).pins() {
+            return Err(Error::InvalidAddress);
+        }
+
+        Ok(PinOutput {
+            bus: self.bus.clone(),
+            pin: pin
+        })
+    }
+
+    pub fn group_input(&mut self, mask: u16) -> Result<GroupInput> {
+        let mut bus = lock(&self.bus);
+        let mask = mask & bus.mask();
+        try!(bus.modify(mask, 0));
+
+        Ok(GroupInput {
+            bus: self.bus.clone(),
+            mask: mask
+        })
+    }
+
+    pub fn group_output(&mut self, mask: u16) -> Result<GroupOutput> {
+        let mask = mask & lock(&self.bus).mask();
+
+        Ok(GroupOutput {
+            bus: self.bus.clone(),
+            mask: mask
+        })
+    }
+}
+
+pub struct PinInput {
+    bus: SharedBus,
+    pin: usize
+}
+
+impl DigitalRead for PinInput {
+    fn digital_read(&mut self) -> Result<Logic> {
+        let mut bus = lock(&self.bus);
+
+        let mask = 1 << self.pin;
+        let val = try!(bus.read());
+
+        match val & mask {
+            0 => Ok(Logic::Low),
+            _ => Ok(Logic::High)
+        }
+    }
+}
+
+pub struct PinOutput {
+    bus: SharedBus,
+    pin: usize
+}
+
+impl DigitalWrite for PinOutput {
+    fn digital_write<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+      let mut bus = lock(&self.bus);
+      let bit: u16 = 1 << self.pin;
+      match level.logic_level() {
+          Logic::Low  => try!(bus.modify(0, bit)),
+          Logic::High => try!(bus.modify(bit, 0))
+      }
+      Ok(())
+    }
+}
+
+impl Drop for PinOutput {
+    fn drop(&mut self) {
+        let mut bus = lock(&self.bus);
+        // release pin
+        let _ = bus.modify(1 << self.pin, 0);
+    }
+}
+
+pub struct GroupInput {
+    bus: SharedBus,
+    mask: u16
+}
+
+impl GroupInput {
+    pub fn digital_read(&mut self) -> Result<u16> {
+        let mut bus = lock(&self.bus);
+        let val = try!(bus.read());
+        Ok(val & self.mask)
+    }
+}
+
+pub struct GroupOutput {
+    bus: SharedBus,
+    mask: u16
+}
+
+impl GroupOutput {
+    // Sets masked bits to value, other pins keep their latch
+    pub fn write_bits(&mut self, value: u16) -> Result<()> {
+        let mut bus = lock(&self.bus);
+        try!(bus.modify(value & self.mask, !value & self.mask));
+        Ok(())
+    }
+}
+
+impl DigitalWrite for GroupOutput {
+    fn digital_write<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+      let mut bus = lock(&self.bus);
+      match level.logic_level() {
+          Logic::Low  => try!(bus.modify(0, self.mask)),
+          Logic::High => try!(bus.modify(self.mask, 0))
+      }
+      Ok(())
+    }
+}
+
+impl Drop for GroupOutput {
+    fn drop(&mut self) {
+        let mut bus = lock(&self.bus);
+        // release pins
+        let _ = bus.modify(self.mask, 0);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {Logic, DigitalRead, DigitalWrite};
+    use super::super::{PCF857X, Simulator};
+
+    #[test]
+    fn quasi_bidirectional() {
+        let sim = Simulator::new(8);
+        let chip = PCF857X::pcf8574(sim.clone()).unwrap();
+        assert_eq!(sim.latch(), 0xFF);
+
+        let mut relay = chip.port().output(0).unwrap();
+        let mut button = chip.port().input(1).unwrap();
+        relay.low().unwrap();
+        assert_eq!(sim.latch(), 0xFE);
+
+        // reading the input must not touch the shadowed outputs
+        sim.set_inputs(0xFD);
+        assert_eq!(button.digital_read().unwrap(), Logic::Low);
+        relay.high().unwrap();
+        relay.low().unwrap();
+        assert_eq!(sim.latch(), 0xFE);
+
+        drop(relay);
+        assert_eq!(sim.latch(), 0xFF);
+
+        assert!(chip.port().input(8).is_err());
+        assert!(chip.port().output(8).is_err());
+    }
+
+    #[test]
+    fn pcf8575_groups() {
+        let sim = Simulator::new(16);
+        let chip = PCF857X::pcf8575(sim.clone()).unwrap();
+        let mut lcd = chip.port().group_output(0xFF00).unwrap();
+        let mut keys = chip.port().group_input(0x000F).unwrap();
+
+        let before = sim.transactions();
+        lcd.write_bits(0x5A00).unwrap();
+        assert_eq!(sim.transactions(), before + 1);
+        assert_eq!(sim.latch(), 0x5AFF);
+
+        sim.set_inputs(0xFFF6);
+        assert_eq!(keys.digital_read().unwrap(), 0x0006);
+    }
+}
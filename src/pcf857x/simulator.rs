@@ -0,0 +1,94 @@
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use super::Transport;
+
+struct State {
+    pins: usize,
+    latch: u16,
+    // levels driven from outside, high when not pulled down
+    inputs: u16,
+    interrupt: bool,
+    transactions: usize
+}
+
+impl State {
+    fn levels(&self) -> u16 {
+        let mask = if self.pins == 16 { 0xFFFF } else { 0x00FF };
+        self.latch & self.inputs & mask
+    }
+}
+
+// In-memory PCF8574 (8 pins) or PCF8575 (16 pins) implementing Transport.
+// Clones share the chip, so a test can keep one to drive the pins.
+#[derive(Clone)]
+pub struct Simulator(Arc<Mutex<State>>);
+
+impl Simulator {
+    pub fn new(pins: usize) -> Simulator {
+        assert!(pins == 8 || pins == 16);
+        Simulator(Arc::new(Mutex::new(State {
+            pins: pins,
+            latch: 0xFFFF,
+            inputs: 0xFFFF,
+            interrupt: false,
+            transactions: 0
+        })))
+    }
+
+    fn state(&self) -> MutexGuard<State> {
+        match self.0.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    // Pulls pins low where value bits are clear, raises INT on a change
+    pub fn set_inputs(&self, value: u16) {
+        let mut state = self.state();
+        let before = state.levels();
+        state.inputs = value;
+        if state.levels() != before {
+            state.interrupt = true;
+        }
+    }
+
+    pub fn latch(&self) -> u16 {
+        let state = self.state();
+        let mask = if state.pins == 16 { 0xFFFF } else { 0x00FF };
+        state.latch & mask
+    }
+
+    // INT pin asserted (active low on the chip)
+    pub fn interrupt(&self) -> bool {
+        self.state().interrupt
+    }
+
+    // Number of transport calls (one per I2C transaction)
+    pub fn transactions(&self) -> usize {
+        self.state().transactions
+    }
+}
+
+impl Transport for Simulator {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let mut state = self.state();
+        state.transactions += 1;
+        // reading the port releases INT
+        state.interrupt = false;
+        let levels = state.levels();
+        for (i, byte) in buf.iter_mut().take(state.pins / 8).enumerate() {
+            *byte = (levels >> (8 * i)) as u8;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut state = self.state();
+        state.transactions += 1;
+        state.interrupt = false;
+        for (i, &byte) in data.iter().take(state.pins / 8).enumerate() {
+            state.latch = (state.latch & !(0xFF << (8 * i))) | (byte as u16) << (8 * i);
+        }
+        Ok(())
+    }
+}
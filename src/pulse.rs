@@ -0,0 +1,216 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use sys::{self, Edge};
+use {Result, Logic, DigitalRead, Clock, MonotonicClock, monotonic};
+
+// Measures the length of a pulse at `level`, like Arduino pulseIn.
+// Busy-polls the pin, so it works with any DigitalRead (native pins for best resolution).
+pub fn pulse_in<I: DigitalRead>(pin: &mut I, level: Logic, timeout: Duration) -> Result<Option<Duration>> {
+    pulse_in_with_clock(pin, level, timeout, &MonotonicClock)
+}
+
+pub fn pulse_in_with_clock<I: DigitalRead, C: Clock>(pin: &mut I, level: Logic, timeout: Duration, clock: &C) -> Result<Option<Duration>> {
+    let deadline = clock.now() + timeout;
+
+    // wait for the previous pulse to end
+    while try!(pin.digital_read()) == level {
+        if clock.now() >= deadline { return Ok(None) }
+    }
+    // wait for the pulse to start
+    while try!(pin.digital_read()) != level {
+        if clock.now() >= deadline { return Ok(None) }
+    }
+    let start = clock.now();
+    while try!(pin.digital_read()) == level {
+        if clock.now() >= deadline { return Ok(None) }
+    }
+    Ok(Some(clock.now() - start))
+}
+
+// Same as pulse_in, but sleeps on sysfs edge events instead of busy-polling.
+// sysfs has no kernel timestamps, an event is stamped when it is read, so
+// wakeup latency ends up in the width. Not more accurate than pulse_in.
+pub fn pulse_in_events(pin: &mut sys::PinInput, level: Logic, timeout: Duration) -> Result<Option<Duration>> {
+    let deadline = monotonic() + timeout;
+    let remaining = |now: Duration| if now >= deadline { None } else { Some(deadline - now) };
+
+    let mut event = match try!(pin.wait_for_edge(Edge::BothEdges, Some(timeout))) {
+        Some(event) => event,
+        None => return Ok(None)
+    };
+    // skip the tail of a pulse that was already running
+    while event.level != level {
+        event = match remaining(monotonic()) {
+            Some(left) => match try!(pin.next_event(Some(left))) {
+                Some(event) => event,
+                None => return Ok(None)
+            },
+            None => return Ok(None)
+        };
+    }
+    let start = event.timestamp;
+    while event.level == level {
+        event = match remaining(monotonic()) {
+            Some(left) => match try!(pin.next_event(Some(left))) {
+                Some(event) => event,
+                None => return Ok(None)
+            },
+            None => return Ok(None)
+        };
+    }
+    Ok(Some(event.timestamp - start))
+}
+
+pub struct PulseIn<I> {
+    pin: I,
+    timeout: Duration
+}
+
+impl<I> PulseIn<I> {
+    pub fn new(pin: I, timeout: Duration) -> PulseIn<I> {
+        PulseIn { pin: pin, timeout: timeout }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn into_inner(self) -> I {
+        self.pin
+    }
+}
+
+impl<I: DigitalRead> PulseIn<I> {
+    pub fn high(&mut self) -> Result<Option<Duration>> {
+        pulse_in(&mut self.pin, Logic::High, self.timeout)
+    }
+
+    pub fn low(&mut self) -> Result<Option<Duration>> {
+        pulse_in(&mut self.pin, Logic::Low, self.timeout)
+    }
+}
+
+impl PulseIn<sys::PinInput> {
+    pub fn high_events(&mut self) -> Result<Option<Duration>> {
+        pulse_in_events(&mut self.pin, Logic::High, self.timeout)
+    }
+
+    pub fn low_events(&mut self) -> Result<Option<Duration>> {
+        pulse_in_events(&mut self.pin, Logic::Low, self.timeout)
+    }
+}
+
+// Edges per second over a sliding window.
+// Arm the pin with sys::PinInput::trigger and call handle() on each readiness event.
+pub struct FrequencyCounter<C = MonotonicClock> {
+    window: Duration,
+    edges: VecDeque<Duration>,
+    clock: C
+}
+
+impl FrequencyCounter {
+    pub fn new(window: Duration) -> FrequencyCounter {
+        FrequencyCounter::with_clock(window, MonotonicClock)
+    }
+}
+
+impl<C: Clock> FrequencyCounter<C> {
+    pub fn with_clock(window: Duration, clock: C) -> FrequencyCounter<C> {
+        FrequencyCounter { window: window, edges: VecDeque::new(), clock: clock }
+    }
+
+    pub fn handle(&mut self, pin: &mut sys::PinInput) -> Result<()> {
+        let event = try!(pin.event());
+        self.record_at(event.timestamp);
+        Ok(())
+    }
+
+    pub fn record(&mut self) {
+        let now = self.clock.now();
+        self.record_at(now);
+    }
+
+    pub fn record_at(&mut self, timestamp: Duration) {
+        self.edges.push_back(timestamp);
+        self.expire(timestamp);
+    }
+
+    pub fn count(&mut self) -> usize {
+        let now = self.clock.now();
+        self.expire(now);
+        self.edges.len()
+    }
+
+    pub fn frequency(&mut self) -> f64 {
+        let count = self.count() as f64;
+        let window = self.window.as_secs() as f64 + self.window.subsec_nanos() as f64 * 1e-9;
+        if window == 0.0 { 0.0 } else { count / window }
+    }
+
+    pub fn reset(&mut self) {
+        self.edges.clear();
+    }
+
+    fn expire(&mut self, now: Duration) {
+        while let Some(&first) = self.edges.front() {
+            if first + self.window > now {
+                break;
+            }
+            self.edges.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use {Result, Logic, DigitalRead, Clock};
+    use time::FakeClock;
+    use super::{pulse_in, pulse_in_with_clock, FrequencyCounter};
+
+    // Each read takes a millisecond on the clock
+    struct Sequence(Vec<Logic>, FakeClock);
+
+    impl DigitalRead for Sequence {
+        fn digital_read(&mut self) -> Result<Logic> {
+            self.1.advance(1);
+            if self.0.len() > 1 { Ok(self.0.remove(0)) } else { Ok(self.0[0]) }
+        }
+    }
+
+    #[test]
+    fn pulse_in_measures() {
+        let clock = FakeClock::new(Duration::from_secs(1));
+        let levels = vec![Logic::High, Logic::Low, Logic::High, Logic::High, Logic::High, Logic::Low];
+        let mut pin = Sequence(levels, clock.clone());
+        // started by the 3rd read, ended by the 6th
+        let width = pulse_in_with_clock(&mut pin, Logic::High, Duration::from_millis(10), &clock).unwrap();
+        assert_eq!(width, Some(Duration::from_millis(3)));
+    }
+
+    #[test]
+    fn pulse_in_timeout() {
+        let clock = FakeClock::new(Duration::from_secs(1));
+        let mut pin = Sequence(vec![Logic::Low], clock.clone());
+        assert_eq!(pulse_in_with_clock(&mut pin, Logic::High, Duration::from_millis(5), &clock).unwrap(), None);
+        assert_eq!(clock.now(), Duration::from_millis(1005));
+
+        let mut pin = Sequence(vec![Logic::Low], clock.clone());
+        assert_eq!(pulse_in(&mut pin, Logic::High, Duration::from_millis(1)).unwrap(), None);
+    }
+
+    #[test]
+    fn frequency_sliding_window() {
+        let clock = FakeClock::new(Duration::from_secs(10));
+        let mut counter = FrequencyCounter::with_clock(Duration::from_secs(1), clock.clone());
+        for _ in 0..10 {
+            counter.record();
+            clock.advance(100);
+        }
+        assert_eq!(counter.count(), 9);
+        assert_eq!(counter.frequency(), 9.0);
+
+        clock.advance(1000);
+        assert_eq!(counter.frequency(), 0.0);
+    }
+}
@@ -1,5 +1,6 @@
 use std::result;
 use std::io::Error as IoError;
+use std::io::ErrorKind;
 use std::convert::From;
 use std::string::FromUtf8Error;
 use core::num::ParseIntError;
@@ -17,6 +18,10 @@ pub enum Error {
     UnexpectedError,
     UnsupportedHardware,
     UnconnectedPin,
+    InvalidAddress,
+    DeviceNotResponding,
+    // Modbus exception code returned by the slave
+    ModbusException(u8),
     Map(MapError),
     Io(IoError),
 }
@@ -29,6 +34,16 @@ impl From<IoError> for Error {
     }
 }
 
+// For io::Result based traits (SPI, I2C) built on GPIO pins
+impl From<Error> for IoError {
+    fn from(err: Error) -> IoError {
+        match err {
+            Io(err) => err,
+            err => IoError::new(ErrorKind::Other, format!("{:?}", err))
+        }
+    }
+}
+
 impl From<MapError> for Error {
     fn from(err: MapError) -> Error {
         Map(err)
@@ -0,0 +1,104 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use {Result, board};
+
+mod port;
+#[cfg(feature = "tokio")]
+mod stream;
+#[cfg(test)]
+pub(crate) mod pty;
+
+pub use self::port::Serial;
+
+#[cfg(feature = "tokio")]
+pub use self::stream::SerialStream;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Odd,
+    Even
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StopBits {
+    One,
+    Two
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FlowControl {
+    None,
+    // RTS/CTS
+    Hardware,
+    // XON/XOFF
+    Software
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SerialConfig {
+    baud: u32,
+    data_bits: u8,
+    parity: Parity,
+    stop_bits: StopBits,
+    flow_control: FlowControl,
+    timeout: Option<Duration>
+}
+
+impl Default for SerialConfig {
+    fn default() -> SerialConfig {
+        SerialConfig::new()
+    }
+}
+
+impl SerialConfig {
+    // 9600 8N1, no flow control, reads block until data
+    pub fn new() -> SerialConfig {
+        SerialConfig {
+            baud: 9600,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+            timeout: None
+        }
+    }
+
+    pub fn baud(&mut self, baud: u32) -> &mut Self {
+        self.baud = baud; self
+    }
+
+    // 5-8
+    pub fn data_bits(&mut self, bits: u8) -> &mut Self {
+        self.data_bits = bits; self
+    }
+
+    pub fn parity(&mut self, parity: Parity) -> &mut Self {
+        self.parity = parity; self
+    }
+
+    pub fn stop_bits(&mut self, stop_bits: StopBits) -> &mut Self {
+        self.stop_bits = stop_bits; self
+    }
+
+    pub fn flow_control(&mut self, flow_control: FlowControl) -> &mut Self {
+        self.flow_control = flow_control; self
+    }
+
+    // read fails with TimedOut when nothing arrives in time
+    pub fn timeout(&mut self, timeout: Option<Duration>) -> &mut Self {
+        self.timeout = timeout; self
+    }
+}
+
+// Device of the UART on GPIO14 (TXD) / GPIO15 (RXD). /dev/serial0 follows
+// the firmware overlays (disable-bt, miniuart-bt), the board model is the
+// fallback on older images.
+pub fn gpio_uart() -> Result<PathBuf> {
+    let alias = Path::new("/dev/serial0");
+    if alias.exists() {
+        return Ok(try!(fs::canonicalize(alias)));
+    }
+    Ok(PathBuf::from(try!(board().uart())))
+}
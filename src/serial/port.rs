@@ -0,0 +1,282 @@
+use std::io::prelude::*;
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::time::Duration;
+use std::fs::{OpenOptions, File};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{RawFd, AsRawFd, FromRawFd};
+use libc;
+use mio::{Token, Evented, Ready, PollOpt, Poll};
+use mio::unix::EventedFd;
+use sys;
+use Result;
+use super::{SerialConfig, Parity, StopBits, FlowControl, gpio_uart};
+
+fn speed(baud: u32) -> io::Result<libc::speed_t> {
+    Ok(match baud {
+        50 => libc::B50,
+        75 => libc::B75,
+        110 => libc::B110,
+        134 => libc::B134,
+        150 => libc::B150,
+        200 => libc::B200,
+        300 => libc::B300,
+        600 => libc::B600,
+        1200 => libc::B1200,
+        1800 => libc::B1800,
+        2400 => libc::B2400,
+        4800 => libc::B4800,
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        460800 => libc::B460800,
+        500000 => libc::B500000,
+        576000 => libc::B576000,
+        921600 => libc::B921600,
+        1000000 => libc::B1000000,
+        1152000 => libc::B1152000,
+        1500000 => libc::B1500000,
+        2000000 => libc::B2000000,
+        2500000 => libc::B2500000,
+        3000000 => libc::B3000000,
+        3500000 => libc::B3500000,
+        4000000 => libc::B4000000,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "unsupported baud rate"))
+    })
+}
+
+fn check(res: libc::c_int) -> io::Result<()> {
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+// Serial port over termios, raw mode
+pub struct Serial {
+    file: File,
+    baud: u32,
+    timeout: Option<Duration>
+}
+
+impl Serial {
+    pub fn open<P: AsRef<Path>>(path: P, config: &SerialConfig) -> Result<Serial> {
+        let file = try!(OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(path));
+        let mut serial = Serial { file: file, baud: 0, timeout: None };
+        try!(serial.configure(config));
+        Ok(serial)
+    }
+
+    // UART on GPIO14/15 of this board
+    pub fn open_gpio(config: &SerialConfig) -> Result<Serial> {
+        Serial::open(try!(gpio_uart()), config)
+    }
+
+    pub fn configure(&mut self, config: &SerialConfig) -> Result<()> {
+        let fd = self.file.as_raw_fd();
+        let speed = try!(speed(config.baud));
+        let size = match config.data_bits {
+            5 => libc::CS5,
+            6 => libc::CS6,
+            7 => libc::CS7,
+            8 => libc::CS8,
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "data bits out of 5-8").into())
+        };
+
+        let mut tty: libc::termios = unsafe { mem::zeroed() };
+        try!(check(unsafe { libc::tcgetattr(fd, &mut tty) }));
+        unsafe { libc::cfmakeraw(&mut tty) };
+
+        tty.c_cflag &= !(libc::CSIZE | libc::PARENB | libc::PARODD | libc::CSTOPB | libc::CRTSCTS);
+        tty.c_cflag |= libc::CREAD | libc::CLOCAL | size;
+        tty.c_iflag &= !(libc::INPCK | libc::IXON | libc::IXOFF | libc::IXANY);
+        match config.parity {
+            Parity::None => (),
+            Parity::Odd => {
+                tty.c_cflag |= libc::PARENB | libc::PARODD;
+                tty.c_iflag |= libc::INPCK;
+            },
+            Parity::Even => {
+                tty.c_cflag |= libc::PARENB;
+                tty.c_iflag |= libc::INPCK;
+            }
+        }
+        if config.stop_bits == StopBits::Two {
+            tty.c_cflag |= libc::CSTOPB;
+        }
+        match config.flow_control {
+            FlowControl::None => (),
+            FlowControl::Hardware => tty.c_cflag |= libc::CRTSCTS,
+            FlowControl::Software => tty.c_iflag |= libc::IXON | libc::IXOFF
+        }
+        // read returns as soon as one byte is there, timeouts are done with poll
+        tty.c_cc[libc::VMIN] = 1;
+        tty.c_cc[libc::VTIME] = 0;
+
+        try!(check(unsafe { libc::cfsetispeed(&mut tty, speed) }));
+        try!(check(unsafe { libc::cfsetospeed(&mut tty, speed) }));
+        try!(check(unsafe { libc::tcsetattr(fd, libc::TCSANOW, &tty) }));
+        self.baud = config.baud;
+        self.timeout = config.timeout;
+        Ok(())
+    }
+
+    pub fn baud(&self) -> u32 {
+        self.baud
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    // Required before registering with mio
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        let fd = self.file.as_raw_fd();
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+        try!(check(flags));
+        let flags = if nonblocking { flags | libc::O_NONBLOCK } else { flags & !libc::O_NONBLOCK };
+        try!(check(unsafe { libc::fcntl(fd, libc::F_SETFL, flags) }));
+        Ok(())
+    }
+
+    // Bytes received but not read yet
+    pub fn available(&self) -> Result<usize> {
+        let mut count: libc::c_int = 0;
+        try!(check(unsafe { libc::ioctl(self.file.as_raw_fd(), libc::FIONREAD, &mut count) }));
+        Ok(count as usize)
+    }
+
+    // Drops unread input and unsent output
+    pub fn discard(&mut self) -> Result<()> {
+        try!(check(unsafe { libc::tcflush(self.file.as_raw_fd(), libc::TCIOFLUSH) }));
+        Ok(())
+    }
+
+    #[cfg(feature = "tokio")]
+    pub fn into_stream(self) -> Result<super::SerialStream> {
+        super::stream::SerialStream::new(self)
+    }
+
+    #[cfg(feature = "tokio")]
+    pub(crate) fn file(&mut self) -> &mut File {
+        &mut self.file
+    }
+}
+
+// Already configured port, e.g. the master side of a pty
+impl FromRawFd for Serial {
+    unsafe fn from_raw_fd(fd: RawFd) -> Serial {
+        Serial { file: File::from_raw_fd(fd), baud: SerialConfig::new().baud, timeout: None }
+    }
+}
+
+impl AsRawFd for Serial {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl Read for Serial {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(timeout) = self.timeout {
+            let mut pollfd = [libc::pollfd { fd: self.file.as_raw_fd(), events: libc::POLLIN, revents: 0 }];
+            if try!(sys::poll(&mut pollfd, Some(timeout))) == 0 {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "serial read timed out"));
+            }
+        }
+        self.file.read(buf)
+    }
+}
+
+impl Write for Serial {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    // Waits until the output is on the wire
+    fn flush(&mut self) -> io::Result<()> {
+        check(unsafe { libc::tcdrain(self.file.as_raw_fd()) })
+    }
+}
+
+impl Evented for Serial {
+    fn register(&self, poll: &Poll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.file.as_raw_fd()).register(poll, token, interest, opts)
+    }
+
+    fn reregister(&self, poll: &Poll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.file.as_raw_fd()).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        EventedFd(&self.file.as_raw_fd()).deregister(poll)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::prelude::*;
+    use std::io;
+    use std::time::Duration;
+    use mio::{Events, Poll, PollOpt, Ready, Token};
+    use super::super::{SerialConfig, Parity, StopBits};
+    use super::super::pty::pty;
+    use super::Serial;
+
+    #[test]
+    fn pty_roundtrip() {
+        let (mut master, path) = pty();
+        let mut config = SerialConfig::new();
+        config.baud(115200).parity(Parity::Even).stop_bits(StopBits::Two)
+            .timeout(Some(Duration::from_millis(20)));
+        let mut serial = Serial::open(&path, &config).unwrap();
+
+        serial.write_all(b"AT\r").unwrap();
+        let mut buf = [0u8; 3];
+        master.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"AT\r");
+
+        master.write_all(b"OK").unwrap();
+        let mut buf = [0u8; 2];
+        serial.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"OK");
+
+        let err = serial.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        config.baud(12345);
+        assert!(serial.configure(&config).is_err());
+    }
+
+    #[test]
+    fn mio_readable() {
+        let (mut master, path) = pty();
+        let mut serial = Serial::open(&path, &SerialConfig::new()).unwrap();
+        serial.set_nonblocking(true).unwrap();
+
+        let poll = Poll::new().unwrap();
+        poll.register(&serial, Token(1), Ready::readable(), PollOpt::edge()).unwrap();
+        let mut events = Events::with_capacity(4);
+        let mut buf = [0u8; 8];
+        assert_eq!(serial.read(&mut buf).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+
+        master.write_all(b"$GP").unwrap();
+        poll.poll(&mut events, Some(Duration::from_secs(1))).unwrap();
+        assert_eq!(events.iter().next().unwrap().token(), Token(1));
+        assert_eq!(serial.available().unwrap(), 3);
+        assert_eq!(serial.read(&mut buf).unwrap(), 3);
+    }
+}
@@ -0,0 +1,18 @@
+use std::ffi::CStr;
+use std::os::unix::io::FromRawFd;
+use libc;
+use super::Serial;
+
+// Master side of a pseudo-terminal and the path of its slave, for tests
+pub fn pty() -> (Serial, String) {
+    unsafe {
+        let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        assert!(fd >= 0);
+        assert_eq!(libc::grantpt(fd), 0);
+        assert_eq!(libc::unlockpt(fd), 0);
+        let mut name = [0 as libc::c_char; 64];
+        assert_eq!(libc::ptsname_r(fd, name.as_mut_ptr(), name.len()), 0);
+        let path = CStr::from_ptr(name.as_ptr()).to_string_lossy().into_owned();
+        (Serial::from_raw_fd(fd), path)
+    }
+}
@@ -0,0 +1,76 @@
+use std::io::prelude::*;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::io::unix::AsyncFd;
+use super::Serial;
+use Result;
+
+// Serial port for tokio, must be created inside a runtime.
+// The read timeout of the port does not apply, use tokio::time instead.
+pub struct SerialStream {
+    fd: AsyncFd<Serial>
+}
+
+impl SerialStream {
+    pub(crate) fn new(mut serial: Serial) -> Result<SerialStream> {
+        try!(serial.set_nonblocking(true));
+        Ok(SerialStream { fd: try!(AsyncFd::new(serial)) })
+    }
+
+    pub fn get_mut(&mut self) -> &mut Serial {
+        self.fd.get_mut()
+    }
+
+    pub fn into_inner(self) -> Serial {
+        self.fd.into_inner()
+    }
+}
+
+impl AsyncRead for SerialStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            let mut guard = match this.fd.poll_read_ready_mut(cx) {
+                Poll::Ready(Ok(guard)) => guard,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending
+            };
+            match guard.try_io(|fd| fd.get_mut().file().read(buf.initialize_unfilled())) {
+                Ok(Ok(n)) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                },
+                Ok(Err(err)) => return Poll::Ready(Err(err)),
+                Err(_would_block) => continue
+            }
+        }
+    }
+}
+
+impl AsyncWrite for SerialStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            let mut guard = match this.fd.poll_write_ready_mut(cx) {
+                Poll::Ready(Ok(guard)) => guard,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending
+            };
+            match guard.try_io(|fd| fd.get_mut().file().write(buf)) {
+                Ok(result) => return Poll::Ready(result),
+                Err(_would_block) => continue
+            }
+        }
+    }
+
+    // tcdrain would block the runtime, bytes are with the driver already
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,206 @@
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use {DigitalRead, DigitalWrite, Logic, delay_usec};
+use super::{SpiDevice, SpiConfig, SpiModeFlags, Segment};
+
+struct Pins<SCK, MOSI, MISO> {
+    sck: SCK,
+    mosi: MOSI,
+    miso: MISO
+}
+
+// Software SPI master on any pins (sysfs, direct, expander), timed with
+// delay_usec. Devices share the pins, chip selects come from ChipSelect.
+pub struct SoftSpi<SCK, MOSI, MISO> {
+    pins: Arc<Mutex<Pins<SCK, MOSI, MISO>>>
+}
+
+impl<SCK, MOSI, MISO> Clone for SoftSpi<SCK, MOSI, MISO> {
+    fn clone(&self) -> Self {
+        SoftSpi { pins: self.pins.clone() }
+    }
+}
+
+impl<SCK, MOSI, MISO> SoftSpi<SCK, MOSI, MISO>
+    where SCK: DigitalWrite + Send, MOSI: DigitalWrite + Send, MISO: DigitalRead + Send
+{
+    pub fn new(sck: SCK, mosi: MOSI, miso: MISO) -> SoftSpi<SCK, MOSI, MISO> {
+        SoftSpi { pins: Arc::new(Mutex::new(Pins { sck: sck, mosi: mosi, miso: miso })) }
+    }
+
+    // Mode, speed, bit order and word size of the config are used
+    pub fn device(&self, config: &SpiConfig) -> SoftSpiDevice<SCK, MOSI, MISO> {
+        SoftSpiDevice { bus: self.clone(), config: *config }
+    }
+
+    fn lock(&self) -> MutexGuard<Pins<SCK, MOSI, MISO>> {
+        match self.pins.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+pub struct SoftSpiDevice<SCK, MOSI, MISO> {
+    bus: SoftSpi<SCK, MOSI, MISO>,
+    config: SpiConfig
+}
+
+fn level(bit: bool) -> Logic {
+    if bit { Logic::High } else { Logic::Low }
+}
+
+impl<SCK, MOSI, MISO> Pins<SCK, MOSI, MISO>
+    where SCK: DigitalWrite, MOSI: DigitalWrite, MISO: DigitalRead
+{
+    fn word(&mut self, config: &SpiConfig, half_period: u64, bits: u8, out: u8) -> io::Result<u8> {
+        let cpol = config.mode.contains(SpiModeFlags::SPI_CPOL);
+        let cpha = config.mode.contains(SpiModeFlags::SPI_CPHA);
+        let mut input = 0u8;
+        for i in 0..bits {
+            let n = if config.lsb_first { i } else { bits - 1 - i };
+            let bit = level(out & (1 << n) != 0);
+            // CPHA=0 samples on the leading clock edge, CPHA=1 on the trailing one
+            let sample = if cpha {
+                try!(self.sck.digital_write(level(!cpol)));
+                try!(self.mosi.digital_write(bit));
+                delay_usec(half_period);
+                try!(self.sck.digital_write(level(cpol)));
+                let sample = try!(self.miso.digital_read());
+                delay_usec(half_period);
+                sample
+            } else {
+                try!(self.mosi.digital_write(bit));
+                delay_usec(half_period);
+                try!(self.sck.digital_write(level(!cpol)));
+                let sample = try!(self.miso.digital_read());
+                delay_usec(half_period);
+                try!(self.sck.digital_write(level(cpol)));
+                sample
+            };
+            if sample == Logic::High {
+                input |= 1 << n;
+            }
+        }
+        Ok(input)
+    }
+}
+
+impl<SCK, MOSI, MISO> SpiDevice for SoftSpiDevice<SCK, MOSI, MISO>
+    where SCK: DigitalWrite + Send, MOSI: DigitalWrite + Send, MISO: DigitalRead + Send
+{
+    fn transaction(&mut self, segments: &mut [Segment]) -> io::Result<()> {
+        let mut pins = self.bus.lock();
+        self.clock(&mut pins, segments)
+    }
+
+    fn select_transaction(&mut self, segments: &mut [Segment], select: &mut FnMut(bool) -> io::Result<()>) -> io::Result<()> {
+        let mut pins = self.bus.lock();
+        try!(select(true));
+        let result = self.clock(&mut pins, segments);
+        try!(select(false));
+        result
+    }
+}
+
+impl<SCK, MOSI, MISO> SoftSpiDevice<SCK, MOSI, MISO>
+    where SCK: DigitalWrite, MOSI: DigitalWrite, MISO: DigitalRead
+{
+    fn clock(&self, pins: &mut Pins<SCK, MOSI, MISO>, segments: &mut [Segment]) -> io::Result<()> {
+        // clock idles at CPOL
+        try!(pins.sck.digital_write(level(self.config.mode.contains(SpiModeFlags::SPI_CPOL))));
+
+        for segment in segments.iter_mut() {
+            let speed = if segment.speed > 0 { segment.speed } else { self.config.speed };
+            let half_period = 500_000 / speed.max(1) as u64;
+            let bits = if segment.bits_per_word > 0 { segment.bits_per_word } else { self.config.bits_per_word };
+            if bits == 0 || bits > 8 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "bits per word above 8"));
+            }
+
+            for i in 0..segment.len() {
+                let out = segment.tx.map(|tx| tx[i]).unwrap_or(0);
+                let input = try!(pins.word(&self.config, half_period, bits, out));
+                if let Some(ref mut rx) = segment.rx {
+                    rx[i] = input;
+                }
+            }
+            delay_usec(segment.delay_usecs as u64);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+    use {DigitalRead, DigitalWrite, DigitalLogic, Logic, Result};
+    use super::super::{SpiDevice, SpiConfig, SPI_MODE_0, SPI_MODE_3};
+    use super::SoftSpi;
+
+    // MOSI wired to MISO, SCK edges counted
+    #[derive(Clone)]
+    struct Loopback(Arc<Mutex<(Logic, Vec<Logic>)>>);
+
+    struct Sck(Loopback);
+    struct Mosi(Loopback);
+    struct Miso(Loopback);
+
+    impl DigitalWrite for Sck {
+        fn digital_write<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+            (self.0).0.lock().unwrap().1.push(level.logic_level());
+            Ok(())
+        }
+    }
+
+    impl DigitalWrite for Mosi {
+        fn digital_write<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+            (self.0).0.lock().unwrap().0 = level.logic_level();
+            Ok(())
+        }
+    }
+
+    impl DigitalRead for Miso {
+        fn digital_read(&mut self) -> Result<Logic> {
+            Ok((self.0).0.lock().unwrap().0)
+        }
+    }
+
+    fn transfer(config: &SpiConfig, tx: &[u8]) -> (Vec<u8>, Vec<Logic>) {
+        let wire = Loopback(Arc::new(Mutex::new((Logic::Low, Vec::new()))));
+        let bus = SoftSpi::new(Sck(wire.clone()), Mosi(wire.clone()), Miso(wire.clone()));
+        let mut dev = bus.device(config);
+        let mut rx = vec![0u8; tx.len()];
+        dev.transfer(tx, &mut rx).unwrap();
+        let clock = wire.0.lock().unwrap().1.clone();
+        (rx, clock)
+    }
+
+    #[test]
+    fn modes_loopback() {
+        let mut config = SpiConfig::new();
+        config.speed(10_000_000);
+
+        config.mode(SPI_MODE_0);
+        let (rx, clock) = transfer(&config, &[0xA5, 0x3C]);
+        assert_eq!(rx, vec![0xA5, 0x3C]);
+        assert_eq!(clock[0], Logic::Low);
+        assert_eq!(clock.len(), 1 + 2 * 16);
+
+        config.mode(SPI_MODE_3).lsb_first(true);
+        let (rx, clock) = transfer(&config, &[0x81]);
+        assert_eq!(rx, vec![0x81]);
+        assert_eq!((clock[0], clock[1]), (Logic::High, Logic::Low));
+        assert_eq!(*clock.last().unwrap(), Logic::High);
+    }
+
+    #[test]
+    fn transfer_length_mismatch() {
+        let wire = Loopback(Arc::new(Mutex::new((Logic::Low, Vec::new()))));
+        let bus = SoftSpi::new(Sck(wire.clone()), Mosi(wire.clone()), Miso(wire.clone()));
+        let mut dev = bus.device(&SpiConfig::new());
+        let mut rx = [0u8; 1];
+        assert!(dev.transfer(&[1, 2], &mut rx).is_err());
+        assert!(wire.0.lock().unwrap().1.is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+use std::io;
+use {DigitalWrite, Logic};
+use super::{SpiDevice, Segment};
+
+// Chip select on any GPIO output, for more devices than CE0/CE1.
+// The wrapped device should be configured with chip_select(false).
+pub struct ChipSelect<D, P> {
+    dev: D,
+    pin: P,
+    active: Logic
+}
+
+impl<D: SpiDevice, P: DigitalWrite + Send> ChipSelect<D, P> {
+    // Active low, like CE0/CE1
+    pub fn new(dev: D, pin: P) -> ::Result<ChipSelect<D, P>> {
+        ChipSelect::with_active(dev, pin, Logic::Low)
+    }
+
+    pub fn with_active(dev: D, mut pin: P, active: Logic) -> ::Result<ChipSelect<D, P>> {
+        try!(pin.digital_write(active.inverse()));
+        Ok(ChipSelect { dev: dev, pin: pin, active: active })
+    }
+
+    pub fn into_inner(self) -> (D, P) {
+        (self.dev, self.pin)
+    }
+
+}
+
+impl<D: SpiDevice, P: DigitalWrite + Send> SpiDevice for ChipSelect<D, P> {
+    fn transaction(&mut self, segments: &mut [Segment]) -> io::Result<()> {
+        let mut rest = segments;
+        while !rest.is_empty() {
+            // a cs_change segment ends the selection
+            let n = rest.iter().position(|segment| segment.cs_change).map(|i| i + 1).unwrap_or(rest.len());
+            let (chunk, tail) = { rest }.split_at_mut(n);
+            let pin = &mut self.pin;
+            let active = self.active;
+            try!(self.dev.select_transaction(chunk, &mut |selected| {
+                let level = if selected { active } else { active.inverse() };
+                Ok(try!(pin.digital_write(level)))
+            }));
+            rest = tail;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Barrier, Mutex};
+    use std::thread;
+    use {DigitalWrite, DigitalLogic, Logic, Result};
+    use super::super::{SpiDevice, MockSpiDevice, Segment};
+    use super::ChipSelect;
+
+    struct FakePin(Arc<Mutex<Vec<Logic>>>);
+
+    impl DigitalWrite for FakePin {
+        fn digital_write<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+            self.0.lock().unwrap().push(level.logic_level());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn cs_change_splits_selection() {
+        let levels = Arc::new(Mutex::new(Vec::new()));
+        let mock = MockSpiDevice::new();
+        mock.respond(&[0xAB]);
+        let mut dev = ChipSelect::new(mock.clone(), FakePin(levels.clone())).unwrap();
+
+        let mut rx = [0u8];
+        dev.transaction(&mut [
+            Segment::write(&[0x01]).cs_change(true),
+            Segment::write(&[0x02]),
+            Segment::read(&mut rx)
+        ]).unwrap();
+
+        assert_eq!(rx, [0xAB]);
+        assert_eq!(mock.written(), vec![vec![0x01], vec![0x02, 0x00]]);
+        assert_eq!(*levels.lock().unwrap(), vec![
+            Logic::High,
+            Logic::Low, Logic::High,
+            Logic::Low, Logic::High
+        ]);
+    }
+
+    // Select levels of several pins in one log
+    struct LogPin(usize, Arc<Mutex<Vec<(usize, Logic)>>>);
+
+    impl DigitalWrite for LogPin {
+        fn digital_write<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+            self.1.lock().unwrap().push((self.0, level.logic_level()));
+            // give the other thread a chance to get in between
+            thread::yield_now();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn one_device_selected_at_a_time() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mock = MockSpiDevice::new();
+        let start = Arc::new(Barrier::new(2));
+        let threads: Vec<_> = (0..2).map(|id| {
+            let mut dev = ChipSelect::new(mock.clone(), LogPin(id, log.clone())).unwrap();
+            let start = start.clone();
+            thread::spawn(move || {
+                start.wait();
+                for _ in 0..200 {
+                    dev.write(&[id as u8]).unwrap();
+                }
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let log = log.lock().unwrap();
+        let mut selected = None;
+        for &(id, level) in log[2..].iter() {
+            match level {
+                Logic::Low => {
+                    assert_eq!(selected, None);
+                    selected = Some(id);
+                },
+                Logic::High => {
+                    assert_eq!(selected, Some(id));
+                    selected = None;
+                }
+            }
+        }
+        assert_eq!(mock.transactions(), 400);
+    }
+}
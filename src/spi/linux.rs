@@ -0,0 +1,166 @@
+use std::io;
+use std::mem;
+use std::path::Path;
+use std::ptr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::os::unix::io::AsRawFd;
+use libc;
+use spidev::{self, Spidev, SpidevOptions, SPI_LSB_FIRST, SPI_NO_CS};
+use Result;
+use super::{SpiDevice, SpiConfig, Segment};
+
+// struct spi_ioc_transfer from linux/spi/spidev.h
+#[repr(C)]
+struct SpiIocTransfer {
+    tx_buf: u64,
+    rx_buf: u64,
+    len: u32,
+    speed_hz: u32,
+    delay_usecs: u16,
+    bits_per_word: u8,
+    cs_change: u8,
+    tx_nbits: u8,
+    rx_nbits: u8,
+    word_delay_usecs: u8,
+    pad: u8
+}
+
+// SPI_IOC_MESSAGE(n), the ioctl size field has 14 bits
+fn spi_ioc_message(n: usize) -> io::Result<libc::c_ulong> {
+    let size = n * mem::size_of::<SpiIocTransfer>();
+    if size >= 1 << 14 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "too many segments"));
+    }
+    Ok((0x40000000 | (size << 16) | (0x6B << 8)) as libc::c_ulong)
+}
+
+struct Bus {
+    spi: Spidev,
+    // settings the spidev handle currently has
+    config: Option<SpiConfig>
+}
+
+// spidev handle (/dev/spidevB.C) shared by devices with their own settings
+#[derive(Clone)]
+pub struct SpiBus {
+    bus: Arc<Mutex<Bus>>
+}
+
+impl SpiBus {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SpiBus> {
+        let spi = try!(Spidev::open(path));
+        Ok(SpiBus { bus: Arc::new(Mutex::new(Bus { spi: spi, config: None })) })
+    }
+
+    pub fn device(&self, config: &SpiConfig) -> LinuxSpiDevice {
+        LinuxSpiDevice { bus: self.clone(), config: *config }
+    }
+
+    fn lock(&self) -> MutexGuard<Bus> {
+        match self.bus.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+pub struct LinuxSpiDevice {
+    bus: SpiBus,
+    config: SpiConfig
+}
+
+impl LinuxSpiDevice {
+    // Device alone on its spidev handle
+    pub fn open<P: AsRef<Path>>(path: P, config: &SpiConfig) -> Result<LinuxSpiDevice> {
+        let bus = try!(SpiBus::open(path));
+        Ok(bus.device(config))
+    }
+
+    pub fn config(&self) -> &SpiConfig {
+        &self.config
+    }
+}
+
+impl Bus {
+    fn configure(&mut self, config: &SpiConfig) -> io::Result<()> {
+        if self.config.as_ref() == Some(config) {
+            return Ok(());
+        }
+        let mut mode = spidev::SpiModeFlags::from_bits_truncate(config.mode.bits() as u32);
+        if config.lsb_first {
+            mode = mode | SPI_LSB_FIRST;
+        }
+        if !config.chip_select {
+            mode = mode | SPI_NO_CS;
+        }
+        let mut options = SpidevOptions::new();
+        options.bits_per_word(config.bits_per_word)
+               .max_speed_hz(config.speed)
+               .mode(mode);
+        try!(self.spi.configure(&options));
+        self.config = Some(*config);
+        Ok(())
+    }
+
+    // One SPI_IOC_MESSAGE ioctl
+    fn transfer(&mut self, config: &SpiConfig, segments: &mut [Segment]) -> io::Result<()> {
+        try!(self.configure(config));
+
+        let transfers: Vec<SpiIocTransfer> = segments.iter_mut().map(|segment| {
+            let len = segment.len();
+            SpiIocTransfer {
+                tx_buf: segment.tx.map(|tx| tx.as_ptr()).unwrap_or(ptr::null()) as u64,
+                rx_buf: match segment.rx {
+                    Some(ref mut rx) => rx.as_mut_ptr() as u64,
+                    None => 0
+                },
+                len: len as u32,
+                speed_hz: if segment.speed > 0 { segment.speed } else { config.speed },
+                delay_usecs: segment.delay_usecs,
+                bits_per_word: if segment.bits_per_word > 0 { segment.bits_per_word } else { config.bits_per_word },
+                cs_change: segment.cs_change as u8,
+                tx_nbits: 0,
+                rx_nbits: 0,
+                word_delay_usecs: 0,
+                pad: 0
+            }
+        }).collect();
+
+        if transfers.is_empty() {
+            return Ok(());
+        }
+        let request = try!(spi_ioc_message(transfers.len()));
+        let fd = self.spi.as_raw_fd();
+        if unsafe { libc::ioctl(fd, request, transfers.as_ptr()) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl SpiDevice for LinuxSpiDevice {
+    fn transaction(&mut self, segments: &mut [Segment]) -> io::Result<()> {
+        let mut bus = self.bus.lock();
+        bus.transfer(&self.config, segments)
+    }
+
+    fn select_transaction(&mut self, segments: &mut [Segment], select: &mut FnMut(bool) -> io::Result<()>) -> io::Result<()> {
+        let mut bus = self.bus.lock();
+        try!(select(true));
+        let result = bus.transfer(&self.config, segments);
+        try!(select(false));
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::spi_ioc_message;
+
+    #[test]
+    fn message_size_limit() {
+        assert_eq!(spi_ioc_message(1).unwrap(), 0x40206B00);
+        assert!(spi_ioc_message(511).is_ok());
+        assert!(spi_ioc_message(512).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use std::io;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use super::{SpiDevice, Segment};
+
+struct State {
+    written: Vec<Vec<u8>>,
+    responses: VecDeque<u8>,
+    transactions: usize
+}
+
+// Records MOSI and plays back queued MISO bytes. Only segments with an
+// rx buffer take bytes from the queue, an empty queue reads as 0xFF.
+// Clones share the device, so a test can keep one to inspect it.
+#[derive(Clone)]
+pub struct MockSpiDevice(Arc<Mutex<State>>);
+
+impl MockSpiDevice {
+    pub fn new() -> MockSpiDevice {
+        MockSpiDevice(Arc::new(Mutex::new(State {
+            written: Vec::new(),
+            responses: VecDeque::new(),
+            transactions: 0
+        })))
+    }
+
+    fn state(&self) -> MutexGuard<State> {
+        match self.0.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    pub fn respond(&self, data: &[u8]) {
+        self.state().responses.extend(data.iter().cloned());
+    }
+
+    // MOSI bytes of every chip select period
+    pub fn written(&self) -> Vec<Vec<u8>> {
+        self.state().written.clone()
+    }
+
+    pub fn transactions(&self) -> usize {
+        self.state().transactions
+    }
+}
+
+impl SpiDevice for MockSpiDevice {
+    fn transaction(&mut self, segments: &mut [Segment]) -> io::Result<()> {
+        let mut state = self.state();
+        state.transfer(segments)
+    }
+
+    fn select_transaction(&mut self, segments: &mut [Segment], select: &mut FnMut(bool) -> io::Result<()>) -> io::Result<()> {
+        let mut state = self.state();
+        try!(select(true));
+        let result = state.transfer(segments);
+        try!(select(false));
+        result
+    }
+}
+
+impl State {
+    fn transfer(&mut self, segments: &mut [Segment]) -> io::Result<()> {
+        self.transactions += 1;
+        self.written.push(Vec::new());
+        let last = segments.len().saturating_sub(1);
+        for (i, segment) in segments.iter_mut().enumerate() {
+            let len = segment.len();
+            let tx = segment.tx.map(|tx| tx.to_vec()).unwrap_or(vec![0; len]);
+            if let Some(selection) = self.written.last_mut() {
+                selection.extend(tx);
+            }
+            if let Some(ref mut rx) = segment.rx {
+                for byte in rx.iter_mut() {
+                    *byte = self.responses.pop_front().unwrap_or(0xFF);
+                }
+            }
+            if segment.cs_change && i != last {
+                self.written.push(Vec::new());
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use std::io;
+
+#[cfg(feature = "spi")]
+mod linux;
+mod mock;
+mod chip_select;
+mod bitbang;
+
+#[cfg(feature = "spi")]
+pub use self::linux::{SpiBus, LinuxSpiDevice};
+pub use self::mock::MockSpiDevice;
+pub use self::chip_select::ChipSelect;
+pub use self::bitbang::{SoftSpi, SoftSpiDevice};
+
+// Clock polarity and phase, same bits as the kernel's spidev mode.
+// Defined here so the software master does not need spidev.
+bitflags! {
+    pub struct SpiModeFlags: u8 {
+        const SPI_CPHA = 0x01;
+        const SPI_CPOL = 0x02;
+        const SPI_MODE_0 = 0x00;
+        const SPI_MODE_1 = 0x01;
+        const SPI_MODE_2 = 0x02;
+        const SPI_MODE_3 = 0x03;
+    }
+}
+
+pub const SPI_MODE_0: SpiModeFlags = SpiModeFlags::SPI_MODE_0;
+pub const SPI_MODE_1: SpiModeFlags = SpiModeFlags::SPI_MODE_1;
+pub const SPI_MODE_2: SpiModeFlags = SpiModeFlags::SPI_MODE_2;
+pub const SPI_MODE_3: SpiModeFlags = SpiModeFlags::SPI_MODE_3;
+
+// Per-device settings, applied to the bus before each transaction
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpiConfig {
+    mode: SpiModeFlags,
+    speed: u32,
+    bits_per_word: u8,
+    lsb_first: bool,
+    chip_select: bool
+}
+
+impl Default for SpiConfig {
+    fn default() -> SpiConfig {
+        SpiConfig::new()
+    }
+}
+
+impl SpiConfig {
+    // Mode 0, 1 MHz, 8-bit words MSB first, hardware chip select
+    pub fn new() -> SpiConfig {
+        SpiConfig {
+            mode: SPI_MODE_0,
+            speed: 1_000_000,
+            bits_per_word: 8,
+            lsb_first: false,
+            chip_select: true
+        }
+    }
+
+    pub fn mode(&mut self, mode: SpiModeFlags) -> &mut Self {
+        self.mode = mode; self
+    }
+
+    pub fn speed(&mut self, hz: u32) -> &mut Self {
+        self.speed = hz; self
+    }
+
+    pub fn bits_per_word(&mut self, bits: u8) -> &mut Self {
+        self.bits_per_word = bits; self
+    }
+
+    pub fn lsb_first(&mut self, lsb_first: bool) -> &mut Self {
+        self.lsb_first = lsb_first; self
+    }
+
+    // false leaves the CE line alone (SPI_NO_CS), for ChipSelect on a GPIO
+    pub fn chip_select(&mut self, enable: bool) -> &mut Self {
+        self.chip_select = enable; self
+    }
+}
+
+// One transfer of a transaction. Without tx zeros are clocked out,
+// without rx the received bytes are dropped.
+pub struct Segment<'a> {
+    // private so that tx and rx always have the same length
+    tx: Option<&'a [u8]>,
+    rx: Option<&'a mut [u8]>,
+    // wait after the segment, before chip select changes
+    pub delay_usecs: u16,
+    // release chip select after the segment
+    pub cs_change: bool,
+    // 0 uses the device setting
+    pub speed: u32,
+    pub bits_per_word: u8
+}
+
+impl<'a> Segment<'a> {
+    fn new(tx: Option<&'a [u8]>, rx: Option<&'a mut [u8]>) -> Segment<'a> {
+        Segment { tx: tx, rx: rx, delay_usecs: 0, cs_change: false, speed: 0, bits_per_word: 0 }
+    }
+
+    pub fn write(tx: &'a [u8]) -> Segment<'a> {
+        Segment::new(Some(tx), None)
+    }
+
+    pub fn read(rx: &'a mut [u8]) -> Segment<'a> {
+        Segment::new(None, Some(rx))
+    }
+
+    // Full duplex, both buffers must have the same length
+    pub fn transfer(tx: &'a [u8], rx: &'a mut [u8]) -> io::Result<Segment<'a>> {
+        if tx.len() != rx.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "tx and rx lengths differ"));
+        }
+        Ok(Segment::new(Some(tx), Some(rx)))
+    }
+
+    pub fn tx(&self) -> Option<&[u8]> {
+        self.tx
+    }
+
+    pub fn rx(&mut self) -> Option<&mut [u8]> {
+        match self.rx {
+            Some(ref mut rx) => Some(rx),
+            None => None
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match (self.tx, &self.rx) {
+            (Some(tx), _) => tx.len(),
+            (None, &Some(ref rx)) => rx.len(),
+            (None, &None) => 0
+        }
+    }
+
+    pub fn delay_usecs(mut self, usecs: u16) -> Self {
+        self.delay_usecs = usecs; self
+    }
+
+    pub fn cs_change(mut self, change: bool) -> Self {
+        self.cs_change = change; self
+    }
+
+    pub fn speed(mut self, hz: u32) -> Self {
+        self.speed = hz; self
+    }
+
+    pub fn bits_per_word(mut self, bits: u8) -> Self {
+        self.bits_per_word = bits; self
+    }
+}
+
+// One slave on an SPI bus
+pub trait SpiDevice: Send {
+    // Segments are clocked with chip select held, unless a segment asks
+    // for cs_change
+    fn transaction(&mut self, segments: &mut [Segment]) -> io::Result<()>;
+
+    // Transaction with a chip select driven by select(true)/select(false)
+    // while the bus is held, so no other device on the bus is clocked in
+    // between. The default suits devices that have the bus to themselves.
+    fn select_transaction(&mut self, segments: &mut [Segment], select: &mut FnMut(bool) -> io::Result<()>) -> io::Result<()> {
+        try!(select(true));
+        let result = self.transaction(segments);
+        try!(select(false));
+        result
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.transaction(&mut [Segment::write(data)])
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.transaction(&mut [Segment::read(buf)])
+    }
+
+    fn transfer(&mut self, tx: &[u8], rx: &mut [u8]) -> io::Result<()> {
+        let segment = try!(Segment::transfer(tx, rx));
+        self.transaction(&mut [segment])
+    }
+
+    fn transfer_in_place(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let tx = buf.to_vec();
+        self.transfer(&tx, buf)
+    }
+
+    // Command then response, chip select held in between
+    fn write_read(&mut self, tx: &[u8], rx: &mut [u8]) -> io::Result<()> {
+        self.transaction(&mut [Segment::write(tx), Segment::read(rx)])
+    }
+}
@@ -0,0 +1,330 @@
+use std::io;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::os::unix::io::RawFd;
+use libc;
+use sys::{Edge, Event, EdgeSource, PinInput};
+use sys::event::poll;
+use {Result, Error};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EventId(usize);
+
+enum Handler {
+    Callback(Box<FnMut(Event) + Send>),
+    Channel(Sender<Event>)
+}
+
+struct Watch<P> {
+    id: EventId,
+    pin: P,
+    bounce: Option<Duration>,
+    last: Option<Duration>,
+    paused: bool,
+    handler: Handler
+}
+
+impl<P: EdgeSource> Watch<P> {
+    fn dispatch(&mut self) {
+        let event = match self.pin.event() {
+            Ok(event) => event,
+            Err(_) => return
+        };
+        if self.paused {
+            return;
+        }
+        if let (Some(bounce), Some(last)) = (self.bounce, self.last) {
+            if event.timestamp < last + bounce {
+                return;
+            }
+        }
+        self.last = Some(event.timestamp);
+        match self.handler {
+            Handler::Callback(ref mut f) => f(event),
+            Handler::Channel(ref tx) => { let _ = tx.send(event); }
+        }
+    }
+}
+
+enum Command<P> {
+    Add(Watch<P>),
+    Remove(EventId, Sender<Option<P>>),
+    Pause(EventId, bool, Sender<bool>),
+    Shutdown
+}
+
+// Self-pipe used to wake up the worker from poll(2)
+struct Pipe {
+    rd: RawFd,
+    wr: RawFd
+}
+
+impl Pipe {
+    fn new() -> Result<Pipe> {
+        let mut fds = [0 as libc::c_int; 2];
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+            return Err(Error::Io(io::Error::last_os_error()));
+        }
+        Ok(Pipe { rd: fds[0], wr: fds[1] })
+    }
+
+    fn wake(&self) {
+        let buf = [1u8];
+        unsafe { libc::write(self.wr, buf.as_ptr() as *const libc::c_void, 1); }
+    }
+
+    fn drain(&self) {
+        let mut buf = [0u8; 64];
+        unsafe { libc::read(self.rd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()); }
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.rd);
+            libc::close(self.wr);
+        }
+    }
+}
+
+// Background thread that watches sysfs pins and calls handlers on edges,
+// like RPi.GPIO add_event_detect.
+pub struct EventDispatcher<P = PinInput> {
+    commands: Sender<Command<P>>,
+    pipe: Arc<Pipe>,
+    thread: Option<JoinHandle<()>>,
+    next_id: usize
+}
+
+impl<P: EdgeSource + Send + 'static> EventDispatcher<P> {
+    pub fn new() -> Result<EventDispatcher<P>> {
+        let pipe = Arc::new(try!(Pipe::new()));
+        let (tx, rx) = channel();
+        let worker_pipe = pipe.clone();
+        let thread = try!(thread::Builder::new()
+            .name("cupi-events".to_string())
+            .spawn(move || run(worker_pipe, rx)));
+
+        Ok(EventDispatcher {
+            commands: tx,
+            pipe: pipe,
+            thread: Some(thread),
+            next_id: 0
+        })
+    }
+
+    pub fn add<F>(&mut self, pin: P, edge: Edge, bounce: Option<Duration>, callback: F) -> Result<EventId>
+        where F: FnMut(Event) + Send + 'static
+    {
+        self.watch(pin, edge, bounce, Handler::Callback(Box::new(callback)))
+    }
+
+    pub fn add_channel(&mut self, pin: P, edge: Edge, bounce: Option<Duration>, tx: Sender<Event>) -> Result<EventId> {
+        self.watch(pin, edge, bounce, Handler::Channel(tx))
+    }
+
+    // Stops watching and gives the pin back
+    pub fn remove(&mut self, id: EventId) -> Result<P> {
+        let (tx, rx) = channel();
+        try!(self.send(Command::Remove(id, tx)));
+        match rx.recv() {
+            Ok(Some(pin)) => Ok(pin),
+            Ok(None) => Err(unknown_id()),
+            Err(_) => Err(Error::UnexpectedError)
+        }
+    }
+
+    // No handler is called after pause returns
+    pub fn pause(&mut self, id: EventId) -> Result<()> {
+        self.set_paused(id, true)
+    }
+
+    pub fn resume(&mut self, id: EventId) -> Result<()> {
+        self.set_paused(id, false)
+    }
+
+    fn set_paused(&mut self, id: EventId, paused: bool) -> Result<()> {
+        let (tx, rx) = channel();
+        try!(self.send(Command::Pause(id, paused, tx)));
+        match rx.recv() {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(unknown_id()),
+            Err(_) => Err(Error::UnexpectedError)
+        }
+    }
+
+    fn watch(&mut self, mut pin: P, edge: Edge, bounce: Option<Duration>, handler: Handler) -> Result<EventId> {
+        try!(pin.arm(edge));
+        let id = EventId(self.next_id);
+        self.next_id += 1;
+        try!(self.send(Command::Add(Watch {
+            id: id,
+            pin: pin,
+            bounce: bounce,
+            last: None,
+            paused: false,
+            handler: handler
+        })));
+        Ok(id)
+    }
+}
+
+impl<P> EventDispatcher<P> {
+    fn send(&self, cmd: Command<P>) -> Result<()> {
+        if self.commands.send(cmd).is_err() {
+            return Err(Error::UnexpectedError);
+        }
+        self.pipe.wake();
+        Ok(())
+    }
+}
+
+impl<P> Drop for EventDispatcher<P> {
+    fn drop(&mut self) {
+        let _ = self.send(Command::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn unknown_id() -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::NotFound, "no such event id"))
+}
+
+fn run<P: EdgeSource>(pipe: Arc<Pipe>, commands: Receiver<Command<P>>) {
+    let mut watches: Vec<Watch<P>> = Vec::new();
+
+    loop {
+        let mut pollfds = vec![libc::pollfd { fd: pipe.rd, events: libc::POLLIN, revents: 0 }];
+        for watch in &watches {
+            pollfds.push(libc::pollfd { fd: watch.pin.fd(), events: libc::POLLPRI | libc::POLLERR, revents: 0 });
+        }
+
+        if poll(&mut pollfds, None).is_err() {
+            return;
+        }
+
+        for (watch, p) in watches.iter_mut().zip(pollfds[1..].iter()) {
+            if p.revents & (libc::POLLPRI | libc::POLLERR) != 0 {
+                watch.dispatch();
+            }
+        }
+
+        if pollfds[0].revents & libc::POLLIN != 0 {
+            pipe.drain();
+            while let Ok(cmd) = commands.try_recv() {
+                match cmd {
+                    Command::Add(watch) => watches.push(watch),
+                    Command::Remove(id, reply) => {
+                        let pin = match watches.iter().position(|w| w.id == id) {
+                            Some(i) => Some(watches.remove(i).pin),
+                            None => None
+                        };
+                        let _ = reply.send(pin);
+                    },
+                    Command::Pause(id, paused, reply) => {
+                        let mut found = false;
+                        for watch in watches.iter_mut().filter(|w| w.id == id) {
+                            watch.paused = paused;
+                            found = true;
+                        }
+                        let _ = reply.send(found);
+                    },
+                    Command::Shutdown => return
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use std::time::Duration;
+    use std::sync::mpsc::{channel, Receiver, TryRecvError};
+    use sys::fake::{self, FakePin};
+    use Error;
+    use super::{EventDispatcher, Edge, Event};
+
+    fn timestamp(rx: &Receiver<Event>) -> Option<Duration> {
+        rx.recv_timeout(Duration::from_secs(1)).ok().map(|e| e.timestamp)
+    }
+
+    fn ms(ms: u64) -> Option<Duration> {
+        Some(Duration::from_millis(ms))
+    }
+
+    #[test]
+    fn callback_and_channel() {
+        let mut dispatcher: EventDispatcher<FakePin> = EventDispatcher::new().unwrap();
+        let (pin_a, mut line_a) = fake::pin();
+        let (pin_b, mut line_b) = fake::pin();
+        let (tx_a, rx_a) = channel();
+        let (tx_b, rx_b) = channel();
+        dispatcher.add(pin_a, Edge::BothEdges, None, move |event| { let _ = tx_a.send(event); }).unwrap();
+        dispatcher.add_channel(pin_b, Edge::BothEdges, None, tx_b).unwrap();
+
+        line_a.edge(10);
+        assert_eq!(timestamp(&rx_a), ms(10));
+        line_b.edge(20);
+        assert_eq!(timestamp(&rx_b), ms(20));
+        assert!(rx_a.try_recv().is_err());
+    }
+
+    #[test]
+    fn bounce_time() {
+        let mut dispatcher = EventDispatcher::new().unwrap();
+        let (pin, mut line) = fake::pin();
+        let (tx, rx) = channel();
+        dispatcher.add_channel(pin, Edge::BothEdges, Some(Duration::from_millis(20)), tx).unwrap();
+
+        line.edge(10);
+        line.edge(25);
+        line.edge(30);
+        assert!(line.wait_read());
+        assert_eq!(timestamp(&rx), ms(10));
+        assert_eq!(timestamp(&rx), ms(30));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn pause_and_remove() {
+        let mut dispatcher = EventDispatcher::new().unwrap();
+        let (pin, mut line) = fake::pin();
+        let (tx, rx) = channel();
+        let id = dispatcher.add_channel(pin, Edge::BothEdges, None, tx).unwrap();
+
+        dispatcher.pause(id).unwrap();
+        line.edge(10);
+        assert!(line.wait_read());
+        dispatcher.resume(id).unwrap();
+        line.edge(20);
+        assert_eq!(timestamp(&rx), ms(20));
+
+        let _pin = dispatcher.remove(id).unwrap();
+        // the watch and its sender are gone
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        match dispatcher.remove(id) {
+            Err(Error::Io(ref err)) if err.kind() == io::ErrorKind::NotFound => (),
+            _ => panic!("id was removed")
+        }
+        match dispatcher.pause(id) {
+            Err(Error::Io(ref err)) if err.kind() == io::ErrorKind::NotFound => (),
+            _ => panic!("id was removed")
+        }
+    }
+
+    #[test]
+    fn drop_joins_thread() {
+        let mut dispatcher = EventDispatcher::new().unwrap();
+        let (pin, _line) = fake::pin();
+        let (tx, rx) = channel();
+        dispatcher.add_channel(pin, Edge::BothEdges, None, tx).unwrap();
+        drop(dispatcher);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+}
@@ -0,0 +1,113 @@
+use std::io;
+use std::time::Duration;
+use std::os::unix::io::RawFd;
+use libc;
+use sys::{Edge, PinInput};
+use {Result, Logic, monotonic};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub pin: usize,
+    pub level: Logic,
+    pub timestamp: Duration
+}
+
+impl Event {
+    pub fn edge(&self) -> Edge {
+        match self.level {
+            Logic::High => Edge::RisingEdge,
+            Logic::Low  => Edge::FallingEdge
+        }
+    }
+}
+
+// Edge events behind a pollable fd that signals POLLPRI, sysfs PinInput
+// in practice. Used by EventDispatcher and the async streams.
+pub trait EdgeSource {
+    fn arm(&mut self, edge: Edge) -> Result<()>;
+    fn fd(&self) -> RawFd;
+    // Reads the event after a wakeup, also clears the pending notification
+    fn event(&mut self) -> Result<Event>;
+}
+
+// Waits for POLLPRI (sysfs value change) on fds, returns indices of ready fds.
+// An empty result means timeout.
+pub fn poll_pri(fds: &[RawFd], timeout: Option<Duration>) -> Result<Vec<usize>> {
+    let mut pollfds: Vec<libc::pollfd> = fds.iter().map(|&fd| {
+        libc::pollfd { fd: fd, events: libc::POLLPRI | libc::POLLERR, revents: 0 }
+    }).collect();
+
+    try!(poll(&mut pollfds, timeout));
+
+    Ok(pollfds.iter()
+        .enumerate()
+        .filter(|&(_, p)| p.revents & (libc::POLLPRI | libc::POLLERR) != 0)
+        .map(|(i, _)| i)
+        .collect())
+}
+
+// poll(2) restarted on EINTR, returns number of ready fds (0 on timeout)
+pub fn poll(pollfds: &mut [libc::pollfd], timeout: Option<Duration>) -> Result<usize> {
+    let deadline = timeout.map(|t| monotonic() + t);
+
+    loop {
+        let ms = match deadline {
+            Some(deadline) => {
+                let now = monotonic();
+                if now >= deadline {
+                    return Ok(0);
+                }
+                duration_to_ms(deadline - now)
+            },
+            None => -1
+        };
+
+        let res = unsafe { libc::poll(pollfds.as_mut_ptr(), pollfds.len() as libc::nfds_t, ms) };
+        if res < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err.into());
+        }
+        return Ok(res as usize);
+    }
+}
+
+fn duration_to_ms(d: Duration) -> libc::c_int {
+    // round up, so we never wake before the deadline
+    let ms = d.as_secs() * 1000 + ((d.subsec_nanos() + 999_999) / 1_000_000) as u64;
+    if ms > libc::c_int::max_value() as u64 {
+        libc::c_int::max_value()
+    } else {
+        ms as libc::c_int
+    }
+}
+
+pub fn wait_any(pins: &mut [&mut PinInput], edge: Edge, timeout: Option<Duration>) -> Result<Vec<Event>> {
+    let mut fds = Vec::with_capacity(pins.len());
+    for pin in pins.iter_mut() {
+        try!(pin.arm(edge));
+        fds.push(pin.fd());
+    }
+
+    let ready = try!(poll_pri(&fds, timeout));
+    let mut events = Vec::with_capacity(ready.len());
+    for i in ready {
+        events.push(try!(pins[i].event()));
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use super::duration_to_ms;
+
+    #[test]
+    fn duration_rounds_up() {
+        assert_eq!(duration_to_ms(Duration::from_millis(0)), 0);
+        assert_eq!(duration_to_ms(Duration::new(0, 1)), 1);
+        assert_eq!(duration_to_ms(Duration::new(1, 500_000)), 1001);
+    }
+}
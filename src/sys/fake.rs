@@ -0,0 +1,74 @@
+use std::io;
+use std::os::unix::net::UnixStream;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use libc;
+use sys::{Edge, Event, EdgeSource};
+use {Result, Logic, monotonic};
+
+// Edge source over a socket pair for tests. An out-of-band byte raises
+// POLLPRI like a sysfs value change and event() consumes it.
+// The byte is the event timestamp in milliseconds.
+pub struct FakePin {
+    sock: UnixStream,
+    read: Arc<AtomicUsize>
+}
+
+pub struct FakeLine {
+    sock: UnixStream,
+    read: Arc<AtomicUsize>,
+    sent: usize
+}
+
+pub fn pin() -> (FakePin, FakeLine) {
+    let (a, b) = UnixStream::pair().unwrap();
+    let read = Arc::new(AtomicUsize::new(0));
+    (FakePin { sock: a, read: read.clone() }, FakeLine { sock: b, read: read, sent: 0 })
+}
+
+impl EdgeSource for FakePin {
+    fn arm(&mut self, _edge: Edge) -> Result<()> {
+        Ok(())
+    }
+
+    fn fd(&self) -> RawFd {
+        self.sock.as_raw_fd()
+    }
+
+    fn event(&mut self) -> Result<Event> {
+        let mut buf = [0u8];
+        let len = unsafe { libc::recv(self.fd(), buf.as_mut_ptr() as *mut libc::c_void, 1, libc::MSG_OOB) };
+        if len < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        self.read.fetch_add(1, Ordering::SeqCst);
+        Ok(Event { pin: 0, level: Logic::High, timestamp: Duration::from_millis(buf[0] as u64) })
+    }
+}
+
+impl FakeLine {
+    // Only one out-of-band byte can be pending, so this waits for the
+    // previous edge to be read first
+    pub fn edge(&mut self, ms: u8) {
+        assert!(self.wait_read(), "previous edge was not read");
+        let buf = [ms];
+        let len = unsafe { libc::send(self.sock.as_raw_fd(), buf.as_ptr() as *const libc::c_void, 1, libc::MSG_OOB) };
+        assert_eq!(len, 1, "{}", io::Error::last_os_error());
+        self.sent += 1;
+    }
+
+    // true once every edge has been read by the pin
+    pub fn wait_read(&self) -> bool {
+        let deadline = monotonic() + Duration::from_secs(1);
+        while self.read.load(Ordering::SeqCst) < self.sent {
+            if monotonic() > deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        true
+    }
+}
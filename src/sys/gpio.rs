@@ -1,9 +1,13 @@
 use std::io::prelude::*;
 use std::io::SeekFrom;
+use std::time::Duration;
 use std::os::unix::io::{AsRawFd, RawFd};
 use mio::{Poll, Token, Ready, PollOpt};
-use sys::{Edge, Selector, GPIOSelector, GPIOPinSelector};
-use {Result, Error, Logic, DigitalLogic, DigitalWrite, DigitalRead, is_root};
+use sys::{Edge, Event, EdgeSource, Selector, GPIOSelector, GPIOPinSelector};
+use sys::event::poll_pri;
+#[cfg(feature = "tokio")]
+use sys::stream::{PinEvents, WaitForEdge};
+use {Result, Error, Logic, DigitalLogic, DigitalWrite, DigitalRead, IoPin, is_root, monotonic};
 
 #[derive(Debug)]
 pub struct Pin {
@@ -43,6 +47,13 @@ impl Pin {
         let sel = try!(GPIOPinSelector::open(self.pin, "value"));
         Ok(PinOutput { sel: sel, pin: self.pin })
     }
+
+    // Starts as input, value file stays open across direction changes
+    pub fn io(&self) -> Result<PinIO> {
+        try!(GPIOPinSelector::write(self.pin, "direction", "in"));
+        let sel = try!(GPIOPinSelector::open(self.pin, "value"));
+        Ok(PinIO { sel: sel, pin: self.pin })
+    }
 }
 
 impl Drop for Pin {
@@ -53,6 +64,23 @@ impl Drop for Pin {
     }
 }
 
+// Current level from an open value file
+fn read_value(sel: &mut Selector) -> Result<Logic> {
+    try!(sel.seek(SeekFrom::Start(0)));
+    let mut buf = [0u8];
+    let len = try!(sel.read(&mut buf));
+
+    if len == 0 {
+        return Err(Error::UnexpectedError);
+    }
+
+    match buf[0] {
+        b'1' => Ok(Logic::High),
+        b'0' => Ok(Logic::Low),
+        _ => Err(Error::UnexpectedError),
+    }
+}
+
 #[derive(Debug)]
 pub struct PinInput {
     sel: Selector,
@@ -61,19 +89,7 @@ pub struct PinInput {
 
 impl DigitalRead for PinInput {
     fn digital_read(&mut self) -> Result<Logic> {
-        try!(self.sel.seek(SeekFrom::Start(0)));
-        let mut buf = [0u8];
-        let len = try!(self.sel.read(&mut buf));
-
-        if len == 0 {
-            return Err(Error::UnexpectedError);
-        }
-
-        match buf[0] {
-            b'1' => Ok(Logic::High),
-            b'0' => Ok(Logic::Low),
-            _ => Err(Error::UnexpectedError),
-        }
+        read_value(&mut self.sel)
     }
 }
 
@@ -94,6 +110,54 @@ impl PinInput {
         Ok(())
     }
 
+    // Blocks until the edge occurs, returns None on timeout
+    pub fn wait_for_edge(&mut self, edge: Edge, timeout: Option<Duration>) -> Result<Option<Event>> {
+        try!(self.arm(edge));
+        self.next_event(timeout)
+    }
+
+    // Waits for the next edge without re-arming, use after trigger or wait_for_edge
+    pub fn next_event(&mut self, timeout: Option<Duration>) -> Result<Option<Event>> {
+        let ready = try!(poll_pri(&[self.fd()], timeout));
+        if ready.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(try!(self.event())))
+    }
+
+    #[cfg(feature = "tokio")]
+    pub fn events(&mut self, edge: Edge) -> Result<PinEvents> {
+        PinEvents::new(self, edge)
+    }
+
+    // Async version of wait_for_edge, resolves to None on timeout
+    #[cfg(feature = "tokio")]
+    pub fn wait_for_edge_async(&mut self, edge: Edge, timeout: Option<Duration>) -> Result<WaitForEdge> {
+        WaitForEdge::new(self, edge, timeout)
+    }
+
+    // Reads the level after a wakeup, also clears pending notification
+    pub fn event(&mut self) -> Result<Event> {
+        let timestamp = monotonic();
+        let level = try!(self.digital_read());
+        Ok(Event { pin: self.pin, level: level, timestamp: timestamp })
+    }
+
+    pub fn pin(&self) -> usize {
+        self.pin
+    }
+
+    pub(crate) fn arm(&mut self, edge: Edge) -> Result<()> {
+        try!(self.set_edge(edge));
+        // any read resets sysfs notification state
+        let _ = try!(self.digital_read());
+        Ok(())
+    }
+
+    pub(crate) fn fd(&self) -> RawFd {
+        self.sel.as_raw_fd()
+    }
+
     fn set_edge(&mut self, edge: Edge) -> Result<()> {
         try!(GPIOPinSelector::write(self.pin, "edge", match edge {
             Edge::NoInterrupt => "none",
@@ -105,6 +169,20 @@ impl PinInput {
     }
 }
 
+impl EdgeSource for PinInput {
+    fn arm(&mut self, edge: Edge) -> Result<()> {
+        PinInput::arm(self, edge)
+    }
+
+    fn fd(&self) -> RawFd {
+        PinInput::fd(self)
+    }
+
+    fn event(&mut self) -> Result<Event> {
+        PinInput::event(self)
+    }
+}
+
 impl AsRawFd for PinInput {
     fn as_raw_fd(&self) -> RawFd {
         self.sel.as_raw_fd()
@@ -133,3 +211,41 @@ impl AsRawFd for PinOutput {
         self.sel.as_raw_fd()
     }
 }
+
+#[derive(Debug)]
+pub struct PinIO {
+    sel: Selector,
+    pin: usize
+}
+
+impl PinIO {
+    pub fn pin(&self) -> usize {
+        self.pin
+    }
+}
+
+impl IoPin for PinIO {
+    fn set_input(&mut self) -> Result<()> {
+        try!(GPIOPinSelector::write(self.pin, "direction", "in"));
+        Ok(())
+    }
+
+    // "high"/"low" set the level together with the direction, no glitch
+    fn set_output<L: DigitalLogic>(&mut self, level: L) -> Result<()> {
+        try!(GPIOPinSelector::write(self.pin, "direction", match level.logic_level() {
+            Logic::High => "high",
+            Logic::Low => "low",
+        }));
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Logic> {
+        read_value(&mut self.sel)
+    }
+}
+
+impl AsRawFd for PinIO {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sel.as_raw_fd()
+    }
+}
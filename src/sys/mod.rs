@@ -8,6 +8,7 @@ pub use self::gpio::{
     Pin,
     PinInput,
     PinOutput,
+    PinIO,
 };
 
 pub use self::fs::{
@@ -16,10 +17,36 @@ pub use self::fs::{
     GPIOPinSelector
 };
 
+pub use self::event::{
+    Event,
+    EdgeSource,
+    wait_any
+};
+
+#[cfg(feature = "serial")]
+pub(crate) use self::event::poll;
+
+pub use self::dispatcher::{
+    EventDispatcher,
+    EventId
+};
+
+#[cfg(feature = "tokio")]
+pub use self::stream::{
+    PinEvents,
+    WaitForEdge
+};
+
 mod gpio;
 mod fs;
+mod event;
+mod dispatcher;
+#[cfg(test)]
+mod fake;
+#[cfg(feature = "tokio")]
+mod stream;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Edge {
     NoInterrupt,
     RisingEdge,
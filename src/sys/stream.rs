@@ -0,0 +1,107 @@
+use std::future::Future;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::Interest;
+use tokio::io::unix::AsyncFd;
+use tokio::time::{sleep, Sleep};
+use futures_core::Stream;
+use sys::{Edge, Event, EdgeSource, PinInput};
+use Result;
+
+// Stream of edge events, must be created inside a tokio runtime.
+// sysfs signals value changes with POLLPRI, so we register priority interest.
+pub struct PinEvents<'a, P: 'a = PinInput> {
+    pin: &'a mut P,
+    fd: AsyncFd<RawFd>
+}
+
+impl<'a, P: EdgeSource> PinEvents<'a, P> {
+    pub(crate) fn new(pin: &'a mut P, edge: Edge) -> Result<PinEvents<'a, P>> {
+        try!(pin.arm(edge));
+        let fd = try!(AsyncFd::with_interest(pin.fd(), Interest::PRIORITY));
+        Ok(PinEvents { pin: pin, fd: fd })
+    }
+
+    fn poll_event(&mut self, cx: &mut Context) -> Poll<Result<Event>> {
+        match self.fd.poll_read_ready(cx) {
+            Poll::Ready(Ok(mut guard)) => {
+                guard.clear_ready();
+                Poll::Ready(self.pin.event())
+            },
+            Poll::Ready(Err(err)) => Poll::Ready(Err(err.into())),
+            Poll::Pending => Poll::Pending
+        }
+    }
+}
+
+impl<'a, P: EdgeSource> Stream for PinEvents<'a, P> {
+    type Item = Result<Event>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<Event>>> {
+        self.get_mut().poll_event(cx).map(Some)
+    }
+}
+
+// Resolves to None on timeout, like the blocking wait_for_edge
+pub struct WaitForEdge<'a, P: 'a = PinInput> {
+    events: PinEvents<'a, P>,
+    timeout: Option<Pin<Box<Sleep>>>
+}
+
+impl<'a, P: EdgeSource> WaitForEdge<'a, P> {
+    pub(crate) fn new(pin: &'a mut P, edge: Edge, timeout: Option<Duration>) -> Result<WaitForEdge<'a, P>> {
+        Ok(WaitForEdge {
+            events: try!(PinEvents::new(pin, edge)),
+            timeout: timeout.map(|t| Box::pin(sleep(t)))
+        })
+    }
+}
+
+impl<'a, P: EdgeSource> Future for WaitForEdge<'a, P> {
+    type Output = Result<Option<Event>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<Option<Event>>> {
+        let this = self.get_mut();
+        if let Poll::Ready(event) = this.events.poll_event(cx) {
+            return Poll::Ready(event.map(Some));
+        }
+        match this.timeout {
+            Some(ref mut timeout) => timeout.as_mut().poll(cx).map(|_| Ok(None)),
+            None => Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::poll_fn;
+    use std::pin::Pin;
+    use std::time::Duration;
+    use tokio::runtime::Builder;
+    use futures_core::Stream;
+    use sys::Edge;
+    use sys::fake;
+    use super::{PinEvents, WaitForEdge};
+
+    #[test]
+    fn events_and_timeout() {
+        let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+        let _context = runtime.enter();
+        let (mut pin, mut line) = fake::pin();
+        {
+            let mut events = PinEvents::new(&mut pin, Edge::BothEdges).unwrap();
+            line.edge(10);
+            let event = runtime.block_on(poll_fn(|cx| Pin::new(&mut events).poll_next(cx)));
+            assert_eq!(event.unwrap().unwrap().timestamp, Duration::from_millis(10));
+        }
+
+        let wait = WaitForEdge::new(&mut pin, Edge::RisingEdge, Some(Duration::from_millis(20))).unwrap();
+        assert!(runtime.block_on(wait).unwrap().is_none());
+
+        line.edge(20);
+        let wait = WaitForEdge::new(&mut pin, Edge::RisingEdge, Some(Duration::from_secs(1))).unwrap();
+        assert_eq!(runtime.block_on(wait).unwrap().unwrap().timestamp, Duration::from_millis(20));
+    }
+}
@@ -1,7 +1,12 @@
 use std::ptr;
 use std::thread;
 use std::time::Duration;
+#[cfg(test)]
+use std::rc::Rc;
+#[cfg(test)]
+use std::cell::Cell;
 use nix::sys::time::TimeVal;
+use libc;
 
 const USEC_PER_SEC: u64 = 1_000_000;
 const USEC_TO_NANOS: u64 = 1_000;
@@ -54,12 +59,55 @@ pub fn delay_ms(ms: u64) {
     thread::sleep(Duration::from_millis(ms));
 }
 
+#[inline(always)]
+pub fn monotonic() -> Duration {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts); }
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+pub trait Clock {
+    fn now(&self) -> Duration;
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Duration {
+        monotonic()
+    }
+}
+
+// Clock moved by hand in tests, clones share the time
+#[cfg(test)]
+#[derive(Clone, Debug)]
+pub struct FakeClock(Rc<Cell<Duration>>);
+
+#[cfg(test)]
+impl FakeClock {
+    pub fn new(now: Duration) -> FakeClock {
+        FakeClock(Rc::new(Cell::new(now)))
+    }
+
+    pub fn advance(&self, ms: u64) {
+        self.0.set(self.0.get() + Duration::from_millis(ms));
+    }
+}
+
+#[cfg(test)]
+impl Clock for FakeClock {
+    fn now(&self) -> Duration {
+        self.0.get()
+    }
+}
+
 // millis
 // micros
 
 #[cfg(test)]
 mod test {
-    use super::{delay_hard, delay_usec, delay_ms};
+    use super::{delay_hard, delay_usec, delay_ms, monotonic};
 
     #[test]
     fn test_delay_hard() {
@@ -75,4 +123,11 @@ mod test {
     fn test_delay_ms() {
         delay_ms(1); // pause 1 millisecond
     }
+
+    #[test]
+    fn test_monotonic() {
+        let start = monotonic();
+        delay_ms(1);
+        assert!(monotonic() > start);
+    }
 }